@@ -0,0 +1,120 @@
+use super::api::{ConsulConfig, QueryOptions, WriteOptions};
+use super::error::ConsulError;
+use serde_derive::{Deserialize, Serialize};
+use surf::http::Method;
+
+/// ACLLink links a token to a policy or role by ID or Name.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct ACLLink {
+    pub ID: Option<String>,
+    pub Name: Option<String>,
+}
+
+pub type ACLTokenPolicyLink = ACLLink;
+pub type ACLTokenRoleLink = ACLLink;
+
+/// ACLToken represents an ACL Token
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct ACLToken {
+    pub CreateIndex: Option<u64>,
+    pub ModifyIndex: Option<u64>,
+    pub AccessorID: Option<String>,
+    pub SecretID: Option<String>,
+    pub Description: Option<String>,
+    pub Policies: Option<Vec<ACLTokenPolicyLink>>,
+    pub Roles: Option<Vec<ACLTokenRoleLink>>,
+    pub Local: Option<bool>,
+    pub CreateTime: Option<String>,
+    pub Hash: Option<String>,
+    /// Namespace is the namespace the ACLToken is associated with.
+    /// Namespaces are a Consul Enterprise feature.
+    pub Namespace: Option<String>,
+}
+
+impl ConsulConfig {
+    /// acl_create_token is used to create a token. The AccessorID and SecretID
+    /// are generated by Consul when left empty. This needs a token with
+    /// `acl:write`, passed in the options or the client config.
+    pub async fn acl_create_token(
+        &self,
+        token: &ACLToken,
+        q: Option<&WriteOptions>,
+    ) -> Result<ACLToken, ConsulError> {
+        let (out, _) = self
+            .write_json(Method::Put, "/v1/acl/token", token, q)
+            .await?;
+        Ok(out)
+    }
+
+    /// acl_read_token retrieves the full token details given its AccessorID.
+    pub async fn acl_read_token(
+        &self,
+        accessor_id: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<ACLToken, ConsulError> {
+        let path = format!("/v1/acl/token/{}", accessor_id);
+        self.get_json(&path, q).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::acl::{ACLLink, ACLToken};
+    use crate::api::tests::mock_consul;
+    use crate::api::{QueryOptions, WriteOptions};
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use serde_json::Value;
+
+    #[test]
+    fn test_acl_token() {
+        let created = r#"{
+            "AccessorID": "6a1253d2-1785-24fd-91c2-f8e78c745511",
+            "SecretID": "45a3bd52-07c7-47a4-52fd-0745e0cfe967",
+            "Description": "web service token",
+            "Policies": [{"ID": "165d4317-e379-f732-ce70-86278c4558f7", "Name": "web"}],
+            "Local": false,
+            "CreateIndex": 59,
+            "ModifyIndex": 59
+        }"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, created),
+            MockResponse::new(200, created),
+        ]);
+        let consul = mock_consul(&server);
+        let token = ACLToken {
+            Description: Some(String::from("web service token")),
+            Policies: Some(vec![ACLLink {
+                Name: Some(String::from("web")),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let w = WriteOptions {
+            Token: Some(String::from("management")),
+            ..Default::default()
+        };
+        let created = block_on(consul.acl_create_token(&token, Some(&w))).unwrap();
+        let accessor_id = created.AccessorID.unwrap();
+        let q = QueryOptions {
+            Token: Some(String::from("management")),
+            ..Default::default()
+        };
+        let read = block_on(consul.acl_read_token(&accessor_id, Some(&q))).unwrap();
+        assert_eq!(read.AccessorID.as_deref(), Some(accessor_id.as_str()));
+        assert_eq!(read.SecretID, created.SecretID);
+        assert_eq!(read.Policies.unwrap()[0].Name.as_deref(), Some("web"));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/v1/acl/token");
+        assert_eq!(requests[0].header("X-Consul-Token"), Some("management"));
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["Description"], "web service token");
+        assert_eq!(body["Policies"][0]["Name"], "web");
+        assert_eq!(requests[1].path, format!("/v1/acl/token/{}", accessor_id));
+        assert_eq!(requests[1].header("X-Consul-Token"), Some("management"));
+    }
+}
@@ -1,22 +1,54 @@
 use super::agent::{AgentServiceRegistration, ServiceRegisterOpts};
-use super::health::{ServiceAddress, ServiceEntry};
+use super::error::ConsulError;
+use super::health::{HealthStatus, ServiceAddress, ServiceEntry};
+use super::runtime::{read_to_string, sleep, RwLock};
 use super::watch::WatchService;
-use async_std::fs::read_to_string;
-use async_std::sync::{Arc, RwLock};
+use futures_util::future;
+use futures_util::stream::{self, Stream};
+use http_client::isahc::IsahcClient;
+use isahc::config::{
+    CaCertificate, ClientCertificate, Configurable, Dialer, PrivateKey, SslOption,
+};
 use lazy_static::lazy_static;
 use rand::Rng;
+use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
 use serde_yaml;
-use std::collections::{HashMap, LinkedList};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::sync::Arc;
 use std::time;
 use surf;
 use surf::http::Method;
-use surf::{Error, StatusCode};
+use surf::StatusCode;
 use toml;
 
+/// HTTP_ADDR_ENV_NAME defines an environment variable name which sets
+/// the HTTP address if there is no -http-addr specified.
+pub const HTTP_ADDR_ENV_NAME: &str = "CONSUL_HTTP_ADDR";
+
+/// HTTP_SSL_ENV_NAME defines an environment variable name which sets
+/// whether or not to use HTTPS.
+pub const HTTP_SSL_ENV_NAME: &str = "CONSUL_HTTP_SSL";
+
+/// HTTP_TOKEN_ENV_NAME defines an environment variable name which sets
+/// the HTTP token.
+pub const HTTP_TOKEN_ENV_NAME: &str = "CONSUL_HTTP_TOKEN";
+
+/// HTTP_TOKEN_HEADER is the header used to send the ACL token.
+pub const HTTP_TOKEN_HEADER: &str = "X-Consul-Token";
+
+/// HTTP_INDEX_HEADER carries the index a blocking query waits on.
+pub const HTTP_INDEX_HEADER: &str = "X-Consul-Index";
+
+/// DEFAULT_TIMEOUT bounds how long a request may take when no timeout is configured.
+pub const DEFAULT_TIMEOUT: time::Duration = time::Duration::from_secs(30);
+
 lazy_static! {
     pub static ref CONSUL_CONFIG: Arc<RwLock<ConsulConfig>> = {
-        let consul_config = ConsulConfig::default();
+        let mut consul_config = ConsulConfig::default();
+        set_default_token(&mut consul_config.config, env_token());
         let consul_config = RwLock::new(consul_config);
         Arc::new(consul_config)
     };
@@ -35,9 +67,11 @@ pub struct ConsulConfig {
 
 impl Default for ConsulConfig {
     fn default() -> Self {
-        let mut config = Config::default();
-        config.address = Some(String::from("http://127.0.0.1:8500"));
-        config.datacenter = Some(String::from("dc1"));
+        let config = Config {
+            address: Some(String::from("http://127.0.0.1:8500")),
+            datacenter: Some(String::from("dc1")),
+            ..Default::default()
+        };
         ConsulConfig {
             config: Some(config),
             watch_services: None,
@@ -46,66 +80,237 @@ impl Default for ConsulConfig {
 }
 
 impl ConsulConfig {
-    pub async fn load_config(path: &str) -> surf::Result<()> {
-        let content = read_to_string(path).await?;
+    /// builder returns a builder for a standalone config, independent of the
+    /// global `CONSUL_CONFIG`.
+    pub fn builder() -> ConsulConfigBuilder {
+        ConsulConfigBuilder::default()
+    }
+
+    /// from_env returns a config built from the environment variables used by
+    /// the official Consul tooling: `CONSUL_HTTP_ADDR`, `CONSUL_HTTP_TOKEN` and
+    /// `CONSUL_HTTP_SSL`. The address defaults to `127.0.0.1:8500` over http.
+    pub fn from_env() -> Self {
+        let mut consul_config = ConsulConfig::default();
+        let config = consul_config.config.get_or_insert_with(Config::default);
+        let address = env::var(HTTP_ADDR_ENV_NAME)
+            .ok()
+            .filter(|address| !address.is_empty())
+            .unwrap_or_else(|| String::from("127.0.0.1:8500"));
+        let ssl = env::var(HTTP_SSL_ENV_NAME)
+            .map(|ssl| matches!(ssl.as_str(), "1" | "t" | "T" | "true" | "TRUE" | "True"))
+            .unwrap_or_default();
+        config.address = Some(address);
+        config.scheme = Some(String::from(if ssl { "https" } else { "http" }));
+        config.token = env_token();
+        consul_config
+    }
+
+    /// with_tls returns a config talking to the Consul server at address over
+    /// mutual TLS, presenting the PEM-encoded client certificate and key and
+    /// optionally verifying the server against the given CA bundle.
+    pub fn with_tls(address: &str, cert_file: &str, key_file: &str, ca_file: Option<&str>) -> Self {
+        let mut consul_config = ConsulConfig::default();
+        let config = consul_config.config.get_or_insert_with(Config::default);
+        config.address = Some(String::from(address));
+        config.tls_config = Some(TLSConfig {
+            cert_file: Some(String::from(cert_file)),
+            key_file: Some(String::from(key_file)),
+            ca_file: ca_file.map(String::from),
+            ..Default::default()
+        });
+        consul_config
+    }
+
+    /// load_config points the global `CONSUL_CONFIG` at the config and watched
+    /// services read from a YAML or TOML file. A file that cannot be read or
+    /// parsed is an InvalidArgument error.
+    pub async fn load_config(path: &str) -> Result<(), ConsulError> {
+        let invalid =
+            |err: &dyn fmt::Display| ConsulError::InvalidArgument(format!("{}: {}", path, err));
+        let content = read_to_string(path).await.map_err(|err| invalid(&err))?;
         let mut config = ConsulConfig::default();
 
         if path.ends_with(".yml") || path.ends_with(".yaml") {
-            config = serde_yaml::from_str(&content)?;
+            config = serde_yaml::from_str(&content).map_err(|err| invalid(&err))?;
         } else if path.ends_with(".toml") {
-            config = toml::from_str(&content)?;
+            config = toml::from_str(&content).map_err(|err| invalid(&err))?;
         }
 
         let consul_config = CONSUL_CONFIG.clone();
         let mut consul_config = consul_config.write().await;
         consul_config.config = config.config;
+        set_default_token(&mut consul_config.config, env_token());
         consul_config.watch_services = config.watch_services;
         Ok(())
     }
 
-    pub async fn new_request(&self, method: Method, path: &str) -> surf::Result<surf::Request> {
-        let config = self.config.as_ref().expect("consul config is empty");
-        let address = config
-            .address
-            .as_ref()
-            .expect("consul config address is empty");
-        let url = format!("{}{}", address, path);
-        let uri = surf::Url::parse(&url)?;
+    /// reload_with points the global `CONSUL_CONFIG` at the cluster described
+    /// by config, e.g. another address, datacenter or token. The watched
+    /// services are kept. Requests already holding a read lock finish against
+    /// the previous config.
+    pub async fn reload_with(config: Config) {
+        let mut consul_config = CONSUL_CONFIG.write().await;
+        consul_config.config = Some(config);
+        set_default_token(&mut consul_config.config, env_token());
+    }
+
+    pub async fn new_request(
+        &self,
+        method: Method,
+        path: &str,
+    ) -> Result<surf::Request, ConsulError> {
+        let config = self.config.as_ref().ok_or(ConsulError::NotConfigured)?;
+        let mut uri = base_url(config, path)?;
+        if let Some(server_name) = server_name(config)? {
+            // The connection still goes to the configured address, see http_client.
+            uri.set_host(Some(&server_name)).map_err(|err| {
+                ConsulError::InvalidArgument(format!(
+                    "Invalid tls server name {:?}: {}",
+                    server_name, err
+                ))
+            })?;
+        }
         let mut req = surf::Request::new(method, uri);
         req.set_header("Connection", "close");
-        let mut body: HashMap<String, String> = HashMap::new();
-
-        if config.datacenter.is_some() {
-            body.insert(
-                String::from("dc"),
-                String::from(config.datacenter.as_ref().unwrap()),
-            );
+        if let Some(datacenter) = &config.datacenter {
+            append_query(&mut req, "dc", datacenter);
         };
-        if config.namespace.is_some() {
-            body.insert(
-                String::from("ns"),
-                String::from(config.namespace.as_ref().unwrap()),
-            );
+        if let Some(namespace) = &config.namespace {
+            append_query(&mut req, "ns", namespace);
         };
+        let mut body: HashMap<String, String> = HashMap::new();
 
-        if config.wait_time.is_some() {
-            let wait = config.wait_time.as_ref().unwrap().to_string();
-            body.insert(String::from("wait"), wait);
+        if let Some(wait_time) = &config.wait_time {
+            body.insert(String::from("wait"), wait_time.to_string());
         } else {
             body.insert(String::from("wait"), String::from("5s"));
         }
 
-        if config.token.is_some() {
-            body.insert(
-                "X-Consul-Token".to_string(),
-                String::from(config.token.as_ref().unwrap()),
-            );
+        if let Some(token) = config.token.as_deref().filter(|token| !token.is_empty()) {
+            req.set_header(HTTP_TOKEN_HEADER, token);
         };
 
         req.body_json(&body)?;
         Ok(req)
     }
 
+    /// http_client returns the client used to send requests to Consul, with
+    /// the configured timeout and TLS options applied to every request.
+    pub(crate) fn http_client(&self) -> Result<surf::Client, ConsulError> {
+        let config = self.config.as_ref();
+        let timeout = config
+            .and_then(|config| config.timeout)
+            .unwrap_or(DEFAULT_TIMEOUT);
+        let mut builder = isahc::HttpClient::builder().timeout(timeout);
+        let socket = config
+            .and_then(|config| config.address.as_deref())
+            .and_then(unix_socket_path);
+        if let Some(socket) = socket {
+            builder = builder.dial(Dialer::unix_socket(socket));
+        } else if let Some(config) = config {
+            if server_name(config)?.is_some() {
+                // Requests carry the tls server name as their host, so connect
+                // to the configured address explicitly. A host name is
+                // resolved here.
+                let url = base_url(config, "")?;
+                let addr = url
+                    .socket_addrs(|| None)
+                    .map_err(|err| ConsulError::Transport(err.into()))?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| {
+                        ConsulError::InvalidArgument(format!(
+                            "Could not resolve {:?}",
+                            url.as_str()
+                        ))
+                    })?;
+                builder = builder.dial(Dialer::ip_socket(addr));
+            }
+        }
+        if let Some(tls_config) = config.and_then(|config| config.tls_config.as_ref()) {
+            if tls_config.insecure_skip_verify.unwrap_or_default() {
+                builder = builder.ssl_options(
+                    SslOption::DANGER_ACCEPT_INVALID_CERTS | SslOption::DANGER_ACCEPT_INVALID_HOSTS,
+                );
+            }
+            if tls_config.ca_path.is_some() {
+                return Err(ConsulError::InvalidArgument(String::from(
+                    "tls config ca_path is not supported, use ca_file",
+                )));
+            }
+            if tls_config.ca_pem.is_some()
+                || tls_config.cert_pem.is_some()
+                || tls_config.key_pem.is_some()
+            {
+                return Err(ConsulError::InvalidArgument(String::from(
+                    "PEM-encoded tls config is not supported, use ca_file, cert_file and key_file",
+                )));
+            }
+            if let Some(ca_file) = &tls_config.ca_file {
+                builder = builder.ssl_ca_certificate(CaCertificate::file(ca_file));
+            }
+            match (&tls_config.cert_file, &tls_config.key_file) {
+                (Some(cert_file), Some(key_file)) => {
+                    let key = PrivateKey::pem_file(key_file, None);
+                    builder =
+                        builder.ssl_client_certificate(ClientCertificate::pem_file(cert_file, key));
+                }
+                (None, None) => {}
+                _ => {
+                    return Err(ConsulError::InvalidArgument(String::from(
+                        "tls config cert_file and key_file must be set together",
+                    )))
+                }
+            }
+        }
+        let client = builder
+            .build()
+            .map_err(|err| ConsulError::Transport(err.into()))?;
+        let client = IsahcClient::from_client(client);
+        Ok(surf::Client::with_http_client(client))
+    }
+
+    /// on_request installs a hook called after every request with its path,
+    /// method, status and duration. It replaces any hook set before.
+    pub fn on_request(&mut self, hook: Box<dyn Fn(&RequestInfo) + Send + Sync>) {
+        self.config.get_or_insert_with(Config::default).on_request =
+            Some(RequestHook(Arc::from(hook)));
+    }
+
+    /// send sends the request to Consul. Reads are retried according to the
+    /// retry policy when the connection fails or Consul answers with a server
+    /// error; writes are never retried since they are not idempotent.
+    pub(crate) async fn send(&self, req: surf::Request) -> Result<surf::Response, ConsulError> {
+        let client = self.http_client()?;
+        if req.method() != Method::Get {
+            return Ok(self.send_once(&client, req).await?);
+        }
+        let policy = self.retry_policy();
+        let mut backoff = policy.base_backoff;
+        let mut attempt = 1;
+        loop {
+            let res = self.send_once(&client, req.clone()).await;
+            let retry = match &res {
+                Ok(res) => res.status().is_server_error(),
+                Err(_) => true,
+            };
+            if !retry || attempt >= policy.max_attempts {
+                return Ok(res?);
+            }
+            log::debug!("retrying {} in {:?}", req.url(), backoff);
+            sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        self.config
+            .as_ref()
+            .and_then(|config| config.retry.clone())
+            .unwrap_or_default()
+    }
+
     /// service_register is used to register a new service with
     /// the local agent
     ///
@@ -126,7 +331,7 @@ impl ConsulConfig {
     pub async fn service_register(
         &self,
         service: &AgentServiceRegistration,
-    ) -> surf::Result<StatusCode> {
+    ) -> Result<StatusCode, ConsulError> {
         let opts = ServiceRegisterOpts::default();
         let status = self.service_register_self(service, &opts).await?;
         Ok(status)
@@ -136,7 +341,7 @@ impl ConsulConfig {
         &self,
         service: &AgentServiceRegistration,
         opts: &ServiceRegisterOpts,
-    ) -> surf::Result<StatusCode> {
+    ) -> Result<StatusCode, ConsulError> {
         let status = self.service_register_self(service, opts).await?;
         Ok(status)
     }
@@ -145,55 +350,50 @@ impl ConsulConfig {
         &self,
         service: &AgentServiceRegistration,
         opts: &ServiceRegisterOpts,
-    ) -> surf::Result<StatusCode> {
+    ) -> Result<StatusCode, ConsulError> {
         if self.config.is_some() {
             let mut req = self
                 .new_request(Method::Put, "/v1/agent/service/register")
                 .await?;
-            if opts.ReplaceExistingChecks == true {
+            if opts.ReplaceExistingChecks {
                 req.set_query(&opts)?;
             };
             req.body_json(&service)?;
-            let client = surf::Client::new();
-            let res = client.send(req).await?;
+            let res = self.send(req).await?;
             Ok(res.status())
         } else {
-            Err(Error::from_str(StatusCode::BadRequest, "client init err"))
+            Err(ConsulError::NotConfigured)
         }
     }
 
-    /// service_deregister is used to register a new service with
+    /// service_deregister is used to deregister a service from
     /// the local agent
     ///
     /// ```
     /// use consul_rs::api::CONSUL_CONFIG;
     /// use async_std::task::block_on;
-    /// use consul_rs::ConsulTrait;
-    /// use consul_rs::agent::AgentServiceRegistration;
     /// let clone_consul = CONSUL_CONFIG.clone();
     /// let consul = block_on(clone_consul.read());
     /// let service_id = String::from("321");
     /// let s = block_on(consul.service_deregister(service_id)).unwrap();
     /// println!("{}", s);
     /// ```
-    pub async fn service_deregister(&self, service_id: String) -> surf::Result<StatusCode> {
+    pub async fn service_deregister(&self, service_id: String) -> Result<StatusCode, ConsulError> {
         if self.config.is_some() {
             let uri = format!("/v1/agent/service/deregister/{}", service_id);
             let req = self.new_request(Method::Put, &uri).await?;
-            let client = surf::Client::new();
-            let res = client.send(req).await?;
+            let res = self.send(req).await?;
             Ok(res.status())
         } else {
-            Err(Error::from_str(StatusCode::BadRequest, "client init err"))
+            Err(ConsulError::NotConfigured)
         }
     }
 
-    pub async fn watch_services() -> surf::Result<()> {
-        let config =  CONSUL_CONFIG.clone();
+    pub async fn watch_services() -> Result<(), ConsulError> {
+        let config = CONSUL_CONFIG.clone();
         let config = config.read().await;
-        if config.watch_services.is_some() {
+        if let Some(watch_services) = &config.watch_services {
             loop {
-                let watch_services = config.watch_services.as_ref().unwrap();
                 let mut service_await = vec![];
 
                 for watch_service in watch_services.iter() {
@@ -202,11 +402,11 @@ impl ConsulConfig {
                 let mut vv = HashMap::new();
                 for v in service_await.into_iter() {
                     let (key, service_address) = v.await?;
-                    if key != "" {
+                    if !key.is_empty() {
                         vv.insert(key, service_address);
                     }
                 }
-                if vv.len() != 0 {
+                if !vv.is_empty() {
                     let services_addresses = SERVICES_ADDRESS.clone();
                     let mut services_addresses = services_addresses.write().await;
                     for (key, service_address) in vv.iter() {
@@ -218,87 +418,428 @@ impl ConsulConfig {
         Ok(())
     }
 
-    async fn health_service(
+    /// send_once sends a single request and reports it to the on_request hook.
+    async fn send_once(
         &self,
-        watch_service: &WatchService,
-    ) -> surf::Result<(u64, Vec<ServiceEntry>)> {
-        let path = format!("/v1/health/service/{}", watch_service.service_name);
-        if self.config.is_some() {
-            let mut req = self.new_request(Method::Get, &path).await?;
-            let mut query: HashMap<&str, String> = HashMap::new();
-            let default = String::new();
-            let tag = watch_service.tag.as_ref().unwrap_or(&default);
-            if tag != "" {
-                query.insert("tag", tag.to_string());
-            }
-            let services_addresses = SERVICES_ADDRESS.clone();
-            let services_addresses = services_addresses.read().await;
-            let key = format!("{}{}", watch_service.service_name, tag);
-            let service_address = services_addresses.get(&key);
-            let mut index = 0;
-            if service_address.is_some() {
-                let service_address = service_address.unwrap();
-                index = service_address.index;
+        client: &surf::Client,
+        req: surf::Request,
+    ) -> surf::Result<surf::Response> {
+        let hook = self
+            .config
+            .as_ref()
+            .and_then(|config| config.on_request.as_ref());
+        let hook = match hook {
+            Some(hook) => hook,
+            None => return client.send(req).await,
+        };
+        let method = req.method();
+        let path = req.url().path().to_string();
+        let start = time::Instant::now();
+        let res = client.send(req).await;
+        (hook.0)(&RequestInfo {
+            method,
+            path,
+            status: res.as_ref().ok().map(|res| res.status()),
+            elapsed: start.elapsed(),
+        });
+        res
+    }
+
+    /// get_json sends a GET to path with the query options applied and
+    /// decodes the JSON answer. Non-2xx responses are returned as errors.
+    pub async fn get_json<T: DeserializeOwned + Default>(
+        &self,
+        path: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<T, ConsulError> {
+        let (out, _) = self.get_json_meta(path, q).await?;
+        Ok(out)
+    }
+
+    /// get_json_meta is like get_json but also returns the QueryMeta of the
+    /// response, for callers doing blocking queries on the returned index.
+    pub async fn get_json_meta<T: DeserializeOwned + Default>(
+        &self,
+        path: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<(T, QueryMeta), ConsulError> {
+        let mut req = self.new_request(Method::Get, path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        let start = time::Instant::now();
+        let mut res = require_ok(self.send(req).await?).await?;
+        let mut meta = parse_query_meta(&res);
+        meta.RequestTime = start.elapsed();
+        let out = decode_json(&mut res).await?;
+        Ok((out, meta))
+    }
+
+    /// write_json sends body as JSON to path with the given method and the
+    /// write options applied, and decodes the JSON answer. An empty answer,
+    /// as most write endpoints give, decodes as the default of T.
+    pub async fn write_json<B: serde::Serialize, T: DeserializeOwned + Default>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &B,
+        w: Option<&WriteOptions>,
+    ) -> Result<(T, WriteMeta), ConsulError> {
+        let mut req = self.new_request(method, path).await?;
+        if let Some(w) = w {
+            w.apply(&mut req);
+        }
+        req.body_json(body)?;
+        let start = time::Instant::now();
+        let mut res = require_ok(self.send(req).await?).await?;
+        let meta = WriteMeta {
+            RequestTime: start.elapsed(),
+        };
+        let out = decode_json(&mut res).await?;
+        Ok((out, meta))
+    }
+
+    /// health_service is used to query health information
+    /// along with service info for a given service. It can
+    /// additionally filter on a tag and only return passing
+    /// instances.
+    pub async fn health_service(
+        &self,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<ServiceEntry>, ConsulError> {
+        let (out, _) = self
+            .health_service_meta(service, tag, passing_only, q)
+            .await?;
+        Ok(out)
+    }
+
+    /// health_service_meta is like health_service but also returns the
+    /// QueryMeta of the response.
+    pub async fn health_service_meta(
+        &self,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+        q: Option<&QueryOptions>,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta), ConsulError> {
+        let req = self
+            .health_service_request(service, tag, passing_only, q)
+            .await?;
+        self.send_health_service(req).await
+    }
+
+    /// health_service_addresses returns the distinct `host:port` addresses of
+    /// a service's instances. An instance registered without an address is
+    /// reached at its node's address, as Consul DNS does.
+    pub async fn health_service_addresses(
+        &self,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<String>, ConsulError> {
+        let entries = self.health_service(service, tag, passing_only, q).await?;
+        let mut seen = HashSet::new();
+        Ok(entries
+            .iter()
+            .filter_map(ServiceEntry::effective_address)
+            .filter(|address| seen.insert(address.to_owned()))
+            .collect())
+    }
+
+    /// health_service_urls is like health_service_addresses but returns base
+    /// URLs such as `http://10.0.0.1:8080`, ready to hand to an HTTP client.
+    pub async fn health_service_urls(
+        &self,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+        scheme: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<String>, ConsulError> {
+        if scheme.is_empty() {
+            return Err(ConsulError::InvalidArgument(String::from("missing scheme")));
+        }
+        let addresses = self
+            .health_service_addresses(service, tag, passing_only, q)
+            .await?;
+        Ok(addresses
+            .into_iter()
+            .map(|address| format!("{}://{}", scheme, address))
+            .collect())
+    }
+
+    /// health_service_multi is like health_service but filters on several
+    /// tags. Consul only returns the instances carrying all of them.
+    pub async fn health_service_multi(
+        &self,
+        service: &str,
+        tags: &[&str],
+        passing_only: bool,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<ServiceEntry>, ConsulError> {
+        let req = self
+            .health_service_tags_request(service, tags, passing_only, q)
+            .await?;
+        let (out, _) = self.send_health_service(req).await?;
+        Ok(out)
+    }
+
+    /// health_service_instance returns the instance of a service registered
+    /// under the given service ID, whatever its health, or `None` when
+    /// there is no such instance.
+    pub async fn health_service_instance(
+        &self,
+        service: &str,
+        service_id: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Option<ServiceEntry>, ConsulError> {
+        let entries = self.health_service(service, "", false, q).await?;
+        Ok(entries.into_iter().find(|entry| {
+            entry
+                .Service
+                .as_ref()
+                .and_then(|service| service.ID.as_deref())
+                == Some(service_id)
+        }))
+    }
+
+    /// health_service_statuses is like health_service but pairs every entry
+    /// with the rolled up status of its checks, see
+    /// `HealthChecks::aggregates_status`. An entry without checks is passing;
+    /// a check with a missing or unknown status fails the whole query.
+    pub async fn health_service_statuses(
+        &self,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<(ServiceEntry, HealthStatus)>, ConsulError> {
+        let entries = self.health_service(service, tag, passing_only, q).await?;
+        entries
+            .into_iter()
+            .map(|entry| {
+                let status = match &entry.Checks {
+                    Some(checks) => checks.aggregates_status()?,
+                    None => HealthStatus::Passing,
+                };
+                Ok((entry, status))
+            })
+            .collect()
+    }
+
+    async fn send_health_service(
+        &self,
+        req: surf::Request,
+    ) -> Result<(Vec<ServiceEntry>, QueryMeta), ConsulError> {
+        let start = time::Instant::now();
+        let res = self.send(req).await?;
+        let mut meta = parse_query_meta(&res);
+        meta.RequestTime = start.elapsed();
+        let out: Option<Vec<ServiceEntry>> = decode_optional(res).await?;
+        Ok((out.unwrap_or_default(), meta))
+    }
+
+    /// health_service_all_dcs queries a service in every known datacenter
+    /// concurrently and returns the entries keyed by datacenter. A datacenter
+    /// that fails to answer is reported in its own entry rather than failing
+    /// the whole call.
+    pub async fn health_service_all_dcs(
+        &self,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+    ) -> Result<HashMap<String, Result<Vec<ServiceEntry>, ConsulError>>, ConsulError> {
+        let datacenters = self.catalog_datacenters().await?;
+        let queries = datacenters.iter().map(|dc| async move {
+            let q = QueryOptions {
+                Datacenter: Some(dc.to_string()),
+                ..Default::default()
+            };
+            self.health_service(service, tag, passing_only, Some(&q))
+                .await
+        });
+        let results = future::join_all(queries).await;
+        Ok(datacenters.into_iter().zip(results).collect())
+    }
+
+    /// health_service_batch resolves several services concurrently. Each spec
+    /// is a service name, a tag and whether only passing instances are wanted.
+    /// The results are keyed by service name, and a service that fails to
+    /// resolve is reported in its own entry rather than failing the batch.
+    /// Naming a service in more than one spec is rejected, as the results
+    /// would share a key.
+    pub async fn health_service_batch(
+        &self,
+        specs: &[(&str, &str, bool)],
+        q: Option<&QueryOptions>,
+    ) -> Result<HashMap<String, Result<Vec<ServiceEntry>, ConsulError>>, ConsulError> {
+        let mut services = HashSet::new();
+        for (service, _, _) in specs.iter() {
+            if !services.insert(service) {
+                return Err(ConsulError::InvalidArgument(format!(
+                    "service {:?} is named in more than one spec",
+                    service
+                )));
             }
-            query.insert("index", index.to_string());
-
-            if watch_service.passing_only.is_some() {
-                let passing = watch_service.passing_only.unwrap();
-                if passing {
-                    let config = self.config.as_ref().unwrap();
-                    let wait;
-                    if config.wait_time.is_some() {
-                        wait = config.wait_time.as_ref().unwrap().to_string();
-                    } else {
-                        wait = String::from("5s")
+        }
+        let queries = specs.iter().map(|(service, tag, passing_only)| async move {
+            let out = self.health_service(service, tag, *passing_only, q).await;
+            (service.to_string(), out)
+        });
+        Ok(future::join_all(queries).await.into_iter().collect())
+    }
+
+    /// watch_service streams the health entries of a service. It issues
+    /// blocking queries with the `X-Consul-Index` of the previous response
+    /// and only yields when the index changes. An index that goes backwards
+    /// means the server state was reset, so the watch starts over from 0.
+    /// After an error the next query is delayed following the retry policy.
+    pub fn watch_service<'a>(
+        &'a self,
+        service: &'a str,
+        tag: &'a str,
+        passing_only: bool,
+        q: Option<&'a QueryOptions>,
+    ) -> impl Stream<Item = Result<Vec<ServiceEntry>, ConsulError>> + 'a {
+        let policy = self.retry_policy();
+        stream::unfold((0, 0), move |(index, errors)| {
+            let policy = policy.clone();
+            async move {
+                if errors > 0 {
+                    let doublings = errors.min(policy.max_attempts.max(1)) - 1;
+                    sleep(policy.base_backoff * 2u32.saturating_pow(doublings)).await;
+                }
+                let mut index = index;
+                loop {
+                    let res = self
+                        .blocking_health_service(service, tag, passing_only, index, q)
+                        .await;
+                    match res {
+                        // An index of 0 would make the next query return
+                        // immediately instead of blocking.
+                        Ok((new_index, entries)) => match new_index.max(1) {
+                            new_index if new_index < index => index = 0,
+                            new_index if new_index == index => {}
+                            new_index => return Some((Ok(entries), (new_index, 0))),
+                        },
+                        Err(err) => return Some((Err(err), (index, errors + 1))),
                     }
-                    query.insert("passing", "1".to_string());
-                    query.insert("wait", wait);
                 }
-            };
-            req.set_query(&query)?;
-            let uri = req.url().to_string();
-            log::debug!("{}", uri);
-            let client = surf::Client::new();
-            let mut res = client.send(req).await?;
-            let out: Vec<ServiceEntry> = res.body_json().await?;
-            Ok((index, out))
+            }
+        })
+    }
+
+    async fn blocking_health_service(
+        &self,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+        index: u64,
+        q: Option<&QueryOptions>,
+    ) -> Result<(u64, Vec<ServiceEntry>), ConsulError> {
+        let mut req = self
+            .health_service_request(service, tag, passing_only, q)
+            .await?;
+        set_query(&mut req, "index", &index.to_string());
+        if q.and_then(|q| q.WaitTime).is_none() {
+            let wait = self
+                .config
+                .as_ref()
+                .and_then(|config| config.wait_time.as_deref())
+                .unwrap_or("5s");
+            set_query(&mut req, "wait", wait);
+        }
+        let mut res = require_ok(self.send(req).await?).await?;
+        let meta = parse_query_meta(&res);
+        let out: Vec<ServiceEntry> = decode_json(&mut res).await?;
+        Ok((meta.LastIndex, out))
+    }
+
+    async fn health_service_request(
+        &self,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+        q: Option<&QueryOptions>,
+    ) -> Result<surf::Request, ConsulError> {
+        let tags = if tag.is_empty() {
+            &[]
         } else {
-            Err(Error::from_str(StatusCode::BadRequest, "client init err"))
+            std::slice::from_ref(&tag)
+        };
+        self.health_service_tags_request(service, tags, passing_only, q)
+            .await
+    }
+
+    async fn health_service_tags_request(
+        &self,
+        service: &str,
+        tags: &[&str],
+        passing_only: bool,
+        q: Option<&QueryOptions>,
+    ) -> Result<surf::Request, ConsulError> {
+        if self.config.is_none() {
+            return Err(ConsulError::NotConfigured);
+        }
+        let path = format!("/v1/health/service/{}", service);
+        let mut req = self.new_request(Method::Get, &path).await?;
+        for tag in tags.iter().filter(|tag| !tag.is_empty()) {
+            append_query(&mut req, "tag", tag);
+        }
+        if passing_only {
+            append_query_flag(&mut req, "passing");
         }
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        Ok(req)
     }
 
-    async fn get_address(
+    async fn watch_health_service(
         &self,
         watch_service: &WatchService,
-    ) -> surf::Result<(String, ServiceAddress)> {
-        let (cur_index, entry) = self.health_service(watch_service).await?;
-        let mut service_addresses = vec![];
-        let mut service_addresses_link = LinkedList::new();
-        let mut index = 0;
-        for val in entry.iter() {
-            if val.Service.is_some() {
-                let v = val.Service.as_ref().unwrap();
-                if v.Address.is_some() && v.Port.is_some() {
-                    index = v.ModifyIndex.unwrap();
-                    if index == cur_index {
-                        continue;
-                    };
-                    let address = v.Address.as_ref().unwrap();
-                    let port = v.Port.as_ref().unwrap();
-                    let address = format!("{}:{}", address, port);
-                    service_addresses.push(address.to_owned());
-                    service_addresses_link.push_back(address);
-                };
-            };
+    ) -> Result<(u64, Vec<ServiceEntry>), ConsulError> {
+        let tag = watch_service.tag.as_deref().unwrap_or_default();
+        let passing_only = watch_service.passing_only.unwrap_or_default();
+        let mut req = self
+            .health_service_request(&watch_service.service_name, tag, passing_only, None)
+            .await?;
+        let services_addresses = SERVICES_ADDRESS.clone();
+        let services_addresses = services_addresses.read().await;
+        let key = format!("{}{}", watch_service.service_name, tag);
+        let index = services_addresses
+            .get(&key)
+            .map(|service_address| service_address.index)
+            .unwrap_or_default();
+        append_query(&mut req, "index", &index.to_string());
+
+        if passing_only {
+            let config = self.config.as_ref().unwrap();
+            let wait = config.wait_time.as_deref().unwrap_or("5s");
+            append_query(&mut req, "wait", wait);
         };
-        if service_addresses.len() == 0 {
+        let uri = req.url().to_string();
+        log::debug!("{}", uri);
+        let out: Option<Vec<ServiceEntry>> = decode_optional(self.send(req).await?).await?;
+        Ok((index, out.unwrap_or_default()))
+    }
+
+    async fn get_address(
+        &self,
+        watch_service: &WatchService,
+    ) -> Result<(String, ServiceAddress), ConsulError> {
+        let (cur_index, entry) = self.watch_health_service(watch_service).await?;
+        let sort = watch_service.sort.unwrap_or_default();
+        let (index, service_addresses) = service_address(&entry, cur_index, sort);
+        if service_addresses.is_empty() {
             return Ok((String::new(), ServiceAddress::default()));
         };
-        let mut tag = "";
-        if watch_service.tag.is_some() {
-            tag = watch_service.tag.as_ref().unwrap();
-        };
+        let service_addresses_link = service_addresses.iter().cloned().collect();
+        let tag = watch_service.tag.as_deref().unwrap_or_default();
         let key = format!("{}{}", watch_service.service_name, tag);
         let service_addresses = ServiceAddress {
             index,
@@ -309,29 +850,305 @@ impl ConsulConfig {
         Ok((key, service_addresses))
     }
 
-    pub async fn random_policy(&self, service_name: &str, tag: &str) -> surf::Result<String> {
+    pub async fn random_policy(
+        &self,
+        service_name: &str,
+        tag: &str,
+    ) -> Result<String, ConsulError> {
         let key = format!("{}{}", service_name, tag);
         let services_addresses = SERVICES_ADDRESS.clone();
         let services_addresses = services_addresses.read().await;
-        let service_addresses = services_addresses.get(&key);
-        if service_addresses.is_some() {
-            let service_addresses = service_addresses.unwrap();
+        if let Some(service_addresses) = services_addresses.get(&key) {
             let range = service_addresses.address.len();
             if range == 0 {
-                return Err(Error::from_str(
-                    StatusCode::BadRequest,
-                    "consul server address is empty",
-                ));
+                return Err(ConsulError::NoAddress);
             };
             let mut r = rand::thread_rng();
             let idx: usize = r.gen_range(0..range);
             let address = service_addresses.address.get(idx).unwrap();
             return Ok(String::from(address));
         }
-        Err(Error::from_str(
-            StatusCode::BadRequest,
-            "consul server address is empty",
-        ))
+        Err(ConsulError::NoAddress)
+    }
+}
+
+/// service_address collects the `host:port` of every entry whose ModifyIndex
+/// differs from cur_index. Entries that do not advertise a service address fall
+/// back to the address of their node. Duplicates are removed, keeping the first
+/// occurrence, and the result is sorted when sort is set.
+pub(crate) fn service_address(
+    entries: &[ServiceEntry],
+    cur_index: u64,
+    sort: bool,
+) -> (u64, Vec<String>) {
+    let mut index = 0;
+    let mut seen = HashSet::new();
+    let mut service_addresses = vec![];
+    for val in entries.iter() {
+        if let Some(address) = val.effective_address() {
+            index = val
+                .Service
+                .as_ref()
+                .and_then(|service| service.ModifyIndex)
+                .unwrap_or_default();
+            if index == cur_index {
+                continue;
+            };
+            if seen.insert(address.to_owned()) {
+                service_addresses.push(address);
+            }
+        };
+    }
+    if sort {
+        service_addresses.sort();
+    }
+    (index, service_addresses)
+}
+
+/// base_url returns the url of path on the Consul server config points at.
+fn base_url(config: &Config, path: &str) -> Result<surf::Url, ConsulError> {
+    let address = config.address.as_ref().ok_or(ConsulError::NotConfigured)?;
+    let url = if unix_socket_path(address).is_some() {
+        // The socket is selected by the http client, the host only fills the url.
+        format!("http://localhost{}", path)
+    } else if address.contains("://") {
+        format!("{}{}", address, path)
+    } else {
+        let scheme = config.scheme.as_deref().unwrap_or("http");
+        format!("{}://{}{}", scheme, address, path)
+    };
+    surf::Url::parse(&url)
+        .map_err(|err| ConsulError::InvalidArgument(format!("Invalid address {:?}: {}", url, err)))
+}
+
+/// server_name returns the name the server certificate is verified against
+/// and sent as SNI, taken from `TLSConfig.address` without its port. It only
+/// applies to https addresses.
+fn server_name(config: &Config) -> Result<Option<String>, ConsulError> {
+    let tls_address = config
+        .tls_config
+        .as_ref()
+        .and_then(|tls_config| tls_config.address.as_deref())
+        .filter(|address| !address.is_empty());
+    let tls_address = match tls_address {
+        Some(tls_address) => tls_address,
+        None => return Ok(None),
+    };
+    if base_url(config, "")?.scheme() != "https" {
+        return Ok(None);
+    }
+    let url = if tls_address.contains("://") {
+        surf::Url::parse(tls_address)
+    } else {
+        surf::Url::parse(&format!("https://{}", tls_address))
+    };
+    url.ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .map(Some)
+        .ok_or_else(|| {
+            ConsulError::InvalidArgument(format!("Invalid tls address {:?}", tls_address))
+        })
+}
+
+/// env_token returns the token set by `CONSUL_HTTP_TOKEN`, if any.
+fn env_token() -> Option<String> {
+    env::var(HTTP_TOKEN_ENV_NAME)
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+/// set_default_token makes token the default of a config that sets none, so
+/// the environment is only read when a config is built rather than on every
+/// request.
+fn set_default_token(config: &mut Option<Config>, token: Option<String>) {
+    if let Some(config) = config.as_mut() {
+        if config.token.as_deref().unwrap_or_default().is_empty() {
+            config.token = token;
+        }
+    }
+}
+
+/// require_ok turns a response with a non-2xx status into an error carrying
+/// the body Consul answered with.
+pub(crate) async fn require_ok(mut res: surf::Response) -> Result<surf::Response, ConsulError> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+    let body = res.body_string().await.unwrap_or_default();
+    Err(ConsulError::from_response(status, body.trim().to_string()))
+}
+
+/// decode_optional decodes the JSON body of a 2xx response. A 404 is a
+/// missing resource rather than a failure and is returned as `None`.
+pub(crate) async fn decode_optional<T: DeserializeOwned + Default>(
+    res: surf::Response,
+) -> Result<Option<T>, ConsulError> {
+    if res.status() == StatusCode::NotFound {
+        return Ok(None);
+    }
+    let mut res = require_ok(res).await?;
+    Ok(Some(decode_json(&mut res).await?))
+}
+
+/// parse_query_meta reads the query meta data Consul sends as response
+/// headers. RequestTime is left for the caller to fill in.
+pub(crate) fn parse_query_meta(res: &surf::Response) -> QueryMeta {
+    let header = |name: &str| res.header(name).map(|values| values.last().as_str());
+    let number = |name: &str| {
+        header(name)
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or_default()
+    };
+    QueryMeta {
+        LastIndex: number(HTTP_INDEX_HEADER),
+        LastContact: time::Duration::from_millis(number("X-Consul-LastContact")),
+        KnownLeader: header("X-Consul-KnownLeader") == Some("true"),
+        AddressTranslationEnabled: header("X-Consul-Translate-Addresses") == Some("true"),
+        CacheHit: header("X-Cache") == Some("HIT"),
+        CacheAge: time::Duration::from_secs(number("Age")),
+        DefaultACLPolicy: header("X-Consul-Default-ACL-Policy")
+            .unwrap_or_default()
+            .to_string(),
+        ..Default::default()
+    }
+}
+
+/// decode_json reads the response body and decodes it as JSON. Consul
+/// answers some queries with an empty body or `null`, e.g. the health of a
+/// service it has never seen, so both decode as the default of T.
+pub(crate) async fn decode_json<T: DeserializeOwned + Default>(
+    res: &mut surf::Response,
+) -> Result<T, ConsulError> {
+    let body = res.body_bytes().await?;
+    decode_body(&body)
+}
+
+fn decode_body<T: DeserializeOwned + Default>(body: &[u8]) -> Result<T, ConsulError> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(T::default());
+    }
+    let out: Option<T> = serde_json::from_slice(body)?;
+    Ok(out.unwrap_or_default())
+}
+
+/// unix_socket_path returns the socket path of a `unix://` address.
+fn unix_socket_path(address: &str) -> Option<&str> {
+    address
+        .strip_prefix("unix://")
+        .filter(|path| !path.is_empty())
+}
+
+/// join_host_port combines host and port into an address of the form
+/// `host:port`. If host contains a colon, as found in literal IPv6
+/// addresses, the result is enclosed in square brackets, as in `[::1]:80`.
+pub(crate) fn join_host_port(host: &str, port: usize) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// append_query appends a single key/value pair to the request query string,
+/// leaving any parameters already present untouched.
+pub(crate) fn append_query(req: &mut surf::Request, key: &str, value: &str) {
+    let req: &mut surf::http::Request = req.as_mut();
+    req.url_mut().query_pairs_mut().append_pair(key, value);
+}
+
+/// append_query_flag appends a parameter without a value, such as `?stale`.
+pub(crate) fn append_query_flag(req: &mut surf::Request, key: &str) {
+    let req: &mut surf::http::Request = req.as_mut();
+    req.url_mut().query_pairs_mut().append_key_only(key);
+}
+
+/// set_query sets a single key/value pair on the request query string,
+/// replacing any value previously set for the same key. Other parameters,
+/// including bare flags such as `?passing`, are left untouched.
+pub(crate) fn set_query(req: &mut surf::Request, key: &str, value: &str) {
+    // query_pairs yields the decoded form of each raw `&`-separated pair in
+    // order, so zipping them keeps flags without a value as they were.
+    let url = req.url();
+    let kept = url
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .zip(url.query_pairs())
+        .filter(|(_, (k, _))| k != key)
+        .map(|(pair, _)| pair)
+        .collect::<Vec<_>>()
+        .join("&");
+    let req: &mut surf::http::Request = req.as_mut();
+    let url = req.url_mut();
+    url.set_query(
+        Some(&kept)
+            .filter(|kept| !kept.is_empty())
+            .map(String::as_str),
+    );
+    url.query_pairs_mut().append_pair(key, value);
+}
+
+/// ConsulConfigBuilder is used to build a `ConsulConfig` without touching the
+/// global `CONSUL_CONFIG`, e.g. to talk to several datacenters from one process.
+#[derive(Debug, Clone, Default)]
+pub struct ConsulConfigBuilder {
+    config: ConsulConfig,
+}
+
+impl ConsulConfigBuilder {
+    fn config(&mut self) -> &mut Config {
+        self.config.config.get_or_insert_with(Config::default)
+    }
+
+    pub fn address(mut self, address: &str) -> Self {
+        self.config().address = Some(String::from(address));
+        self
+    }
+
+    pub fn token(mut self, token: &str) -> Self {
+        self.config().token = Some(String::from(token));
+        self
+    }
+
+    pub fn datacenter(mut self, datacenter: &str) -> Self {
+        self.config().datacenter = Some(String::from(datacenter));
+        self
+    }
+
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.config().namespace = Some(String::from(namespace));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: time::Duration) -> Self {
+        self.config().timeout = Some(timeout);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.config().retry = Some(retry);
+        self
+    }
+
+    pub fn tls_config(mut self, tls_config: TLSConfig) -> Self {
+        self.config().tls_config = Some(tls_config);
+        self
+    }
+
+    pub fn watch_services(mut self, watch_services: Vec<WatchService>) -> Self {
+        self.config.watch_services = Some(watch_services);
+        self
+    }
+
+    pub fn on_request(mut self, hook: Box<dyn Fn(&RequestInfo) + Send + Sync>) -> Self {
+        self.config.on_request(hook);
+        self
+    }
+
+    pub fn build(self) -> ConsulConfig {
+        self.config
     }
 }
 
@@ -339,10 +1156,12 @@ impl ConsulConfig {
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[allow(non_snake_case)]
 pub struct Config {
-    /// Address is the address of the Consul server
+    /// Address is the address of the Consul server. An address of the form
+    /// `unix:///path/to/consul.sock` talks to the agent over a Unix domain socket.
     pub address: Option<String>,
 
-    /// Scheme is the URI scheme for the Consul server
+    /// Scheme is the URI scheme for the Consul server, used when the address
+    /// does not carry one. Defaults to http.
     pub scheme: Option<String>,
 
     /// Datacenter to use. If not provided, the default agent datacenter is used.
@@ -374,7 +1193,60 @@ pub struct Config {
     /// when no other Namespace is present in the QueryOptions
     pub namespace: Option<String>,
 
+    /// Timeout bounds how long a single request may take, including the
+    /// wait of a blocking query. Defaults to 30 seconds.
+    pub timeout: Option<time::Duration>,
+
+    /// Retry controls how reads are retried on server errors and connection
+    /// failures. Defaults to 3 attempts with a 100ms backoff doubling each time.
+    pub retry: Option<RetryPolicy>,
+
     pub tls_config: Option<TLSConfig>,
+
+    /// OnRequest is called after every HTTP request the client sends, for
+    /// metrics and tracing. It is not read from configuration files.
+    #[serde(skip)]
+    pub on_request: Option<RequestHook>,
+}
+
+/// RequestInfo describes a request the client sent, as given to the
+/// `on_request` hook. Retried reads are reported once per attempt.
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    pub method: Method,
+    pub path: String,
+    /// Status is `None` when no response was received.
+    pub status: Option<StatusCode>,
+    pub elapsed: time::Duration,
+}
+
+/// RequestHook wraps the callback installed with `ConsulConfig::on_request`.
+#[derive(Clone)]
+pub struct RequestHook(Arc<dyn Fn(&RequestInfo) + Send + Sync>);
+
+impl fmt::Debug for RequestHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RequestHook")
+    }
+}
+
+/// RetryPolicy is used to retry idempotent reads against a flaky Consul server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// MaxAttempts is the total number of attempts, including the first one.
+    pub max_attempts: u32,
+
+    /// BaseBackoff is the delay before the first retry, doubled after each attempt.
+    pub base_backoff: time::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_backoff: time::Duration::from_millis(100),
+        }
+    }
 }
 
 /// TLSConfig is used to generate a TLSClientConfig that's useful for talking to
@@ -384,7 +1256,8 @@ pub struct Config {
 pub struct TLSConfig {
     /// Address is the optional address of the Consul server. The port, if any
     /// will be removed from here and this will be set to the ServerName of the
-    /// resulting config.
+    /// resulting config: it is sent as SNI and the server certificate is
+    /// verified against it, while connections still go to Config.address.
     pub address: Option<String>,
 
     /// CAFile is the optional path to the CA certificate used for Consul
@@ -393,6 +1266,7 @@ pub struct TLSConfig {
 
     /// CAPath is the optional path to a directory of CA certificates to use for
     /// Consul communication, defaults to the system bundle if not specified.
+    /// Not supported by the http client, which only takes a CA file.
     pub ca_path: Option<String>,
 
     /// CAPem is the optional PEM-encoded CA certificate used for Consul
@@ -419,6 +1293,24 @@ pub struct TLSConfig {
     pub insecure_skip_verify: Option<bool>,
 }
 
+/// Consistency is the consistency mode used for reads.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Consistency {
+    /// Default is strongly consistent in almost all cases, relying on the
+    /// leader lease to avoid a round trip to the followers.
+    #[default]
+    Default,
+
+    /// Stale allows any Consul server (non-leader) to service
+    /// a read. This allows for lower latency and higher throughput
+    Stale,
+
+    /// Consistent forces the read to be fully consistent.
+    /// This is more expensive but prevents ever performing a stale
+    /// read.
+    Consistent,
+}
+
 /// QueryOptions are used to parameterize a query
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[allow(non_snake_case)]
@@ -433,13 +1325,21 @@ pub struct QueryOptions {
 
     /// AllowStale allows any Consul server (non-leader) to service
     /// a read. This allows for lower latency and higher throughput
+    #[deprecated(note = "use Consistency::Stale")]
     pub AllowStale: Option<bool>,
 
     /// RequireConsistent forces the read to be fully consistent.
     /// This is more expensive but prevents ever performing a stale
     /// read.
+    #[deprecated(note = "use Consistency::Consistent")]
     pub RequireConsistent: Option<bool>,
 
+    /// Consistency selects the consistency mode of reads. When left at
+    /// Default, the deprecated RequireConsistent and AllowStale are honoured,
+    /// in that order.
+    #[serde(default)]
+    pub Consistency: Consistency,
+
     /// UseCache requests that the agent cache results locally. See
     /// https:///www.consul.io/api/features/caching.html for more details on the
     /// semantics.
@@ -486,8 +1386,8 @@ pub struct QueryOptions {
     pub Near: Option<String>,
 
     /// NodeMeta is used to filter results by nodes with the given
-    /// metadata key/value pairs. Currently, only one key/value pair can
-    /// be provided for filtering.
+    /// metadata key/value pairs. Each pair is sent as its own `node-meta`
+    /// parameter and nodes must match all of them.
     pub NodeMeta: Option<HashMap<String, String>>,
 
     /// RelayFactor is used in keyring operations to cause responses to be
@@ -506,32 +1406,1290 @@ pub struct QueryOptions {
     /// Filter requests filtering data prior to it being returned. The string
     /// is a go-bexpr compatible expression.
     pub Filter: Option<String>,
+
+    /// MergeCentralConfig returns a service definition merged with the
+    /// proxy-defaults/global and service-defaults/:service config entries.
+    /// This can be used to ensure a full service definition is returned in the response
+    /// especially when the service might not be written into the catalog that way.
+    pub MergeCentralConfig: Option<bool>,
+}
+
+impl QueryOptions {
+    /// consistency returns the mode to use, falling back to the deprecated
+    /// RequireConsistent, then AllowStale, flags when Consistency is Default.
+    #[allow(deprecated)]
+    fn consistency(&self) -> Consistency {
+        match self.Consistency {
+            Consistency::Default if self.RequireConsistent == Some(true) => Consistency::Consistent,
+            Consistency::Default if self.AllowStale == Some(true) => Consistency::Stale,
+            consistency => consistency,
+        }
+    }
+
+    /// apply sets the query parameters described by the options on the request.
+    pub(crate) fn apply(&self, req: &mut surf::Request) {
+        if let Some(datacenter) = &self.Datacenter {
+            set_query(req, "dc", datacenter);
+        }
+        if let Some(namespace) = &self.Namespace {
+            set_query(req, "ns", namespace);
+        }
+        if let Some(token) = &self.Token {
+            req.set_header(HTTP_TOKEN_HEADER, token.as_str());
+        }
+        if req.method() == Method::Get {
+            match self.consistency() {
+                Consistency::Default => {}
+                Consistency::Stale => append_query_flag(req, "stale"),
+                Consistency::Consistent => append_query_flag(req, "consistent"),
+            }
+        }
+        if self.UseCache == Some(true) {
+            append_query_flag(req, "cached");
+            let mut cache_control = vec![];
+            if let Some(max_age) = self.MaxAge {
+                cache_control.push(format!("max-age={}", max_age.as_secs()));
+            }
+            if let Some(stale_if_error) = self.StaleIfError {
+                cache_control.push(format!("stale-if-error={}", stale_if_error.as_secs()));
+            }
+            if !cache_control.is_empty() {
+                req.set_header("Cache-Control", cache_control.join(", "));
+            }
+        }
+        if let Some(index) = self.WaitIndex {
+            set_query(req, "index", &index.to_string());
+        }
+        if let Some(wait) = self.WaitTime {
+            set_query(req, "wait", &format!("{}ms", wait.as_millis()));
+        }
+        if let Some(near) = &self.Near {
+            append_query(req, "near", near);
+        }
+        if let Some(node_meta) = &self.NodeMeta {
+            let mut pairs: Vec<_> = node_meta.iter().collect();
+            pairs.sort();
+            for (key, value) in pairs {
+                append_query(req, "node-meta", &format!("{}:{}", key, value));
+            }
+        }
+        if let Some(filter) = &self.Filter {
+            append_query(req, "filter", filter);
+        }
+        if self.MergeCentralConfig == Some(true) {
+            append_query_flag(req, "merge-central-config");
+        }
+    }
+}
+
+/// WriteOptions are used to parameterize a write
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct WriteOptions {
+    /// Namespace overrides the `default` namespace
+    /// Note: Namespaces are available only in Consul Enterprise
+    pub Namespace: Option<String>,
+
+    /// Providing a datacenter overwrites the DC provided
+    /// by the Config
+    pub Datacenter: Option<String>,
+
+    /// Token is used to provide a per-request ACL token
+    /// which overrides the agent's default token.
+    pub Token: Option<String>,
+}
+
+impl WriteOptions {
+    /// apply sets the query parameters and headers described by the options on the request.
+    pub(crate) fn apply(&self, req: &mut surf::Request) {
+        if let Some(datacenter) = &self.Datacenter {
+            set_query(req, "dc", datacenter);
+        }
+        if let Some(namespace) = &self.Namespace {
+            set_query(req, "ns", namespace);
+        }
+        if let Some(token) = &self.Token {
+            req.set_header(HTTP_TOKEN_HEADER, token.as_str());
+        }
+    }
+}
+
+/// QueryMeta is used to return meta data about a query
+#[derive(Default, Debug, Clone)]
+#[allow(non_snake_case)]
+pub struct QueryMeta {
+    /// LastIndex. This can be used as a WaitIndex to perform
+    /// a blocking query
+    pub LastIndex: u64,
+
+    /// Time of last contact from the leader for the
+    /// server servicing the request
+    pub LastContact: time::Duration,
+
+    /// Is there a known leader
+    pub KnownLeader: bool,
+
+    /// How long did the request take
+    pub RequestTime: time::Duration,
+
+    /// Is address translation enabled for HTTP responses on this agent
+    pub AddressTranslationEnabled: bool,
+
+    /// CacheHit is true if the result was served from agent-local cache.
+    pub CacheHit: bool,
+
+    /// CacheAge is set if request was ?cached and indicates how stale the cached
+    /// response is.
+    pub CacheAge: time::Duration,
+
+    /// DefaultACLPolicy is used to control the ACL interaction when there is no
+    /// defined policy. This can be "allow" which means ACLs are used to
+    /// deny-list, or "deny" which means ACLs are allow-lists.
+    pub DefaultACLPolicy: String,
+}
+
+/// WriteMeta is used to return meta data about a write
+#[derive(Default, Debug, Clone)]
+#[allow(non_snake_case)]
+pub struct WriteMeta {
+    /// How long did the request take
+    pub RequestTime: time::Duration,
 }
 
 #[cfg(test)]
-mod tests {
-    use crate::api::{SERVICES_ADDRESS, Config};
+pub(crate) mod tests {
+    use crate::agent::{AgentService, AgentServiceRegistration};
+    use crate::api::{
+        decode_body, join_host_port, service_address, set_default_token, unix_socket_path, Config,
+        Consistency, ConsulConfig, QueryOptions, RequestInfo, RetryPolicy, TLSConfig, WriteOptions,
+        HTTP_ADDR_ENV_NAME, HTTP_SSL_ENV_NAME, HTTP_TOKEN_ENV_NAME, HTTP_TOKEN_HEADER,
+    };
+    use crate::catalog::Node;
+    use crate::error::ConsulError;
+    use crate::health::{HealthError, HealthStatus, ServiceEntry};
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use surf::http::Method;
+
+    // Tests touching the process environment must not run concurrently.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    // Tests touching the global CONSUL_CONFIG must not run concurrently
+    // either. it_works panics without a local agent, so poisoning is ignored.
+    static GLOBAL_LOCK: Mutex<()> = Mutex::new(());
+
+    fn lock_global() -> std::sync::MutexGuard<'static, ()> {
+        GLOBAL_LOCK.lock().unwrap_or_else(|err| err.into_inner())
+    }
 
     #[test]
     fn it_works() {
+        let _global = lock_global();
         test_watch_services()
     }
 
     pub fn test_watch_services() {
         use crate::api::CONSUL_CONFIG;
         use crate::watch::WatchService;
-        use async_std::task::block_on;
         let clone_consul = CONSUL_CONFIG.clone();
         let mut consul = block_on(clone_consul.write());
-        let mut config = Config::default();
-        config.datacenter = Some(String::from("dc1"));
-        config.address = Some(String::from("http://127.0.0.1:8500"));
+        let config = Config {
+            datacenter: Some(String::from("dc1")),
+            address: Some(String::from("http://127.0.0.1:8500")),
+            ..Default::default()
+        };
         consul.config = Some(config);
-        let mut service = WatchService::default();
-        service.service_name = String::from("hyat_rust");
-        service.passing_only = Some(true);
+        let service = WatchService {
+            service_name: String::from("hyat_rust"),
+            passing_only: Some(true),
+            ..Default::default()
+        };
         // consul.watch_services = Some(vec![service]);
         let s = block_on(consul.get_address(&service)).unwrap();
         println!("{:?}", s);
     }
+
+    #[test]
+    fn test_health_service_filter() {
+        let consul = ConsulConfig::default();
+        let filter = r#"Checks.Status == "passing" and Service.Tags contains "v 2""#;
+        let q = QueryOptions {
+            Filter: Some(String::from(filter)),
+            ..Default::default()
+        };
+        let req = block_on(consul.health_service_request("web", "", false, Some(&q))).unwrap();
+        let query = req.url().query().unwrap();
+        assert!(!query.contains(' '));
+        assert!(!query.contains('"'));
+        let value = req
+            .url()
+            .query_pairs()
+            .find(|(key, _)| key == "filter")
+            .map(|(_, value)| value.into_owned());
+        assert_eq!(value.as_deref(), Some(filter));
+    }
+
+    #[test]
+    fn test_health_service_node_meta() {
+        let consul = ConsulConfig::default();
+        let mut node_meta = HashMap::new();
+        node_meta.insert(String::from("zone"), String::from("us-east-1a"));
+        node_meta.insert(String::from("rack"), String::from("r1"));
+        let q = QueryOptions {
+            NodeMeta: Some(node_meta),
+            ..Default::default()
+        };
+        let req = block_on(consul.health_service_request("web", "v1", true, Some(&q))).unwrap();
+        let pairs: Vec<(String, String)> = req.url().query_pairs().into_owned().collect();
+        let node_meta: Vec<&str> = pairs
+            .iter()
+            .filter(|(key, _)| key == "node-meta")
+            .map(|(_, value)| value.as_str())
+            .collect();
+        assert_eq!(node_meta, vec!["rack:r1", "zone:us-east-1a"]);
+        assert!(pairs.contains(&(String::from("tag"), String::from("v1"))));
+    }
+
+    #[test]
+    fn test_health_service_near() {
+        let server = MockServer::serve(vec![MockResponse::new(200, "[]")]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            Near: Some(String::from("_agent")),
+            ..Default::default()
+        };
+        block_on(consul.health_service("web", "", false, Some(&q))).unwrap();
+        let path = &server.requests()[0].path;
+        assert!(path.starts_with("/v1/health/service/web?"));
+        assert!(path.contains("near=_agent"));
+    }
+
+    #[test]
+    fn test_health_service_merge_central_config() {
+        let body = r#"[{"Service": {
+            "Kind": "connect-proxy",
+            "ID": "web-sidecar-proxy",
+            "Service": "web-sidecar-proxy",
+            "Port": 21000,
+            "Proxy": {
+                "DestinationServiceName": "web",
+                "LocalServicePort": 8080,
+                "Mode": "transparent",
+                "TransparentProxy": {"OutboundListenerPort": 15001, "DialedDirectly": true},
+                "Config": {"protocol": "http"},
+                "Upstreams": [{"DestinationName": "db", "LocalBindPort": 5432}],
+                "MeshGateway": {"Mode": "local"},
+                "Expose": {}
+            }
+        }}]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body),
+            MockResponse::new(200, "[]"),
+        ]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            MergeCentralConfig: Some(true),
+            ..Default::default()
+        };
+        let entries =
+            block_on(consul.health_service("web-sidecar-proxy", "", false, Some(&q))).unwrap();
+        let proxy = entries[0]
+            .Service
+            .as_ref()
+            .and_then(|service| service.Proxy.as_ref())
+            .unwrap();
+        assert_eq!(proxy.LocalServicePort, Some(8080));
+        assert_eq!(proxy.Config.as_ref().unwrap()["protocol"], "http");
+        let transparent = proxy.TransparentProxy.as_ref().unwrap();
+        assert_eq!(transparent.OutboundListenerPort, Some(15001));
+        assert_eq!(transparent.DialedDirectly, Some(true));
+        let upstreams = proxy.Upstreams.as_ref().unwrap();
+        assert_eq!(upstreams[0].LocalBindPort, Some(5432));
+        assert!(upstreams[0].Config.is_empty());
+        let q = QueryOptions {
+            MergeCentralConfig: Some(false),
+            ..Default::default()
+        };
+        block_on(consul.health_service("web-sidecar-proxy", "", false, Some(&q))).unwrap();
+
+        let requests = server.requests();
+        assert!(requests[0].path.contains("merge-central-config"));
+        assert!(!requests[1].path.contains("merge-central-config"));
+    }
+
+    #[test]
+    fn test_health_service_all_dcs() {
+        let body = r#"[{"Service": {"ID": "web-1", "Service": "web", "Port": 8080}}]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, r#"["dc1", "dc2"]"#),
+            MockResponse::new(200, body),
+            MockResponse::new(403, "Permission denied"),
+        ]);
+        let consul = mock_consul(&server);
+        let results = block_on(consul.health_service_all_dcs("web", "", true)).unwrap();
+        assert_eq!(results.len(), 2);
+        let ok: Vec<_> = results.values().filter_map(|r| r.as_ref().ok()).collect();
+        assert_eq!(ok.len(), 1);
+        assert_eq!(ok[0].len(), 1);
+        let err = results.values().find_map(|r| r.as_ref().err()).unwrap();
+        assert_eq!(err.status(), Some(surf::StatusCode::Forbidden));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/catalog/datacenters");
+        let mut queried: Vec<&str> = requests[1..]
+            .iter()
+            .map(|request| request.path.as_str())
+            .collect();
+        queried.sort_unstable();
+        assert_eq!(
+            queried,
+            vec![
+                "/v1/health/service/web?passing&dc=dc1",
+                "/v1/health/service/web?passing&dc=dc2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_health_service_batch() {
+        let body = r#"[{"Service": {"ID": "svc-1", "Service": "svc", "Port": 8080}}]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body),
+            MockResponse::new(200, body),
+            MockResponse::new(403, "Permission denied"),
+        ]);
+        let consul = mock_consul(&server);
+        let specs = [
+            ("web", "", true),
+            ("db", "primary", true),
+            ("cache", "", false),
+        ];
+        let results = block_on(consul.health_service_batch(&specs, None)).unwrap();
+        let mut keys: Vec<&str> = results.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["cache", "db", "web"]);
+        assert_eq!(results.values().filter(|r| r.is_ok()).count(), 2);
+        assert_eq!(results.values().filter(|r| r.is_err()).count(), 1);
+
+        let mut paths: Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+        paths.sort_unstable();
+        assert_eq!(
+            paths,
+            vec![
+                "/v1/health/service/cache",
+                "/v1/health/service/db?tag=primary&passing",
+                "/v1/health/service/web?passing",
+            ]
+        );
+
+        let specs = [("web", "", true), ("web", "canary", false)];
+        let err = block_on(consul.health_service_batch(&specs, None)).unwrap_err();
+        assert!(matches!(err, ConsulError::InvalidArgument(_)));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn test_join_host_port() {
+        assert_eq!(join_host_port("10.0.0.1", 8500), "10.0.0.1:8500");
+        assert_eq!(join_host_port("fe80::1", 8500), "[fe80::1]:8500");
+        assert_eq!(
+            join_host_port("consul.service.dc1", 8500),
+            "consul.service.dc1:8500"
+        );
+    }
+
+    #[test]
+    fn test_service_address_dedup_and_node_fallback() {
+        let entry = |node: &str, address: Option<&str>, port: usize, index: u64| ServiceEntry {
+            Node: Some(Node {
+                Address: Some(String::from(node)),
+                ..Default::default()
+            }),
+            Service: Some(AgentService {
+                Address: address.map(String::from),
+                Port: Some(port),
+                ModifyIndex: Some(index),
+                ..Default::default()
+            }),
+            Checks: None,
+        };
+        let entries = vec![
+            entry("10.0.0.3", Some("10.0.0.3"), 8080, 11),
+            entry("10.0.0.1", Some("10.0.0.1"), 8080, 12),
+            entry("10.0.0.1", Some("10.0.0.1"), 8080, 13),
+            entry("10.0.0.2", Some(""), 8080, 14),
+            entry("10.0.0.4", None, 9090, 15),
+        ];
+        let (index, addresses) = service_address(&entries, 0, false);
+        assert_eq!(index, 15);
+        assert_eq!(
+            addresses,
+            vec![
+                "10.0.0.3:8080",
+                "10.0.0.1:8080",
+                "10.0.0.2:8080",
+                "10.0.0.4:9090"
+            ]
+        );
+
+        let (_, addresses) = service_address(&entries, 0, true);
+        assert_eq!(
+            addresses,
+            vec![
+                "10.0.0.1:8080",
+                "10.0.0.2:8080",
+                "10.0.0.3:8080",
+                "10.0.0.4:9090"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_options_datacenter() {
+        let consul = ConsulConfig::default();
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(req.url().query(), Some("dc=dc1"));
+
+        let q = QueryOptions {
+            Datacenter: Some(String::from("dc2")),
+            ..Default::default()
+        };
+        let req = block_on(consul.health_service_request("web", "", false, Some(&q))).unwrap();
+        assert_eq!(req.url().query(), Some("dc=dc2"));
+    }
+
+    #[test]
+    fn test_token_header() {
+        let mut consul = ConsulConfig::default();
+        let token =
+            |req: &surf::Request| req.header(HTTP_TOKEN_HEADER).map(|v| v.last().to_string());
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(token(&req), None);
+
+        // The environment token is the default for a config without one.
+        set_default_token(&mut consul.config, Some(String::from("env-token")));
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(token(&req).as_deref(), Some("env-token"));
+
+        consul.config.as_mut().unwrap().token = Some(String::from("client-token"));
+        set_default_token(&mut consul.config, Some(String::from("env-token")));
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(token(&req).as_deref(), Some("client-token"));
+
+        let q = QueryOptions {
+            Token: Some(String::from("request-token")),
+            ..Default::default()
+        };
+        let req = block_on(consul.health_service_request("web", "", false, Some(&q))).unwrap();
+        assert_eq!(token(&req).as_deref(), Some("request-token"));
+    }
+
+    #[test]
+    fn test_query_options_namespace() {
+        let consul = ConsulConfig::default();
+        let q = QueryOptions {
+            Namespace: Some(String::from("team-a")),
+            ..Default::default()
+        };
+        let req = block_on(consul.health_service_request("web", "", false, Some(&q))).unwrap();
+        assert_eq!(req.url().query(), Some("dc=dc1&ns=team-a"));
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        use std::net::TcpListener;
+        use std::time::{Duration, Instant};
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = Config {
+            address: Some(format!("http://{}", listener.local_addr().unwrap())),
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let consul = ConsulConfig {
+            config: Some(config),
+            watch_services: None,
+        };
+        let start = Instant::now();
+        let res = block_on(consul.health_service("web", "", false, None));
+        assert!(res.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_https_address() {
+        let config = Config {
+            address: Some(String::from("https://consul.example.com:8501")),
+            tls_config: Some(TLSConfig {
+                insecure_skip_verify: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let consul = ConsulConfig {
+            config: Some(config),
+            watch_services: None,
+        };
+        assert!(consul.http_client().is_ok());
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(req.url().scheme(), "https");
+        assert_eq!(req.url().port(), Some(8501));
+
+        let config = Config {
+            address: Some(String::from("consul.example.com:8501")),
+            scheme: Some(String::from("https")),
+            ..Default::default()
+        };
+        let consul = ConsulConfig {
+            config: Some(config),
+            watch_services: None,
+        };
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(
+            req.url().as_str(),
+            "https://consul.example.com:8501/v1/health/service/web"
+        );
+    }
+
+    #[test]
+    fn test_with_tls() {
+        let consul = ConsulConfig::with_tls(
+            "https://127.0.0.1:8501",
+            "/etc/consul/client.pem",
+            "/etc/consul/client-key.pem",
+            Some("/etc/consul/ca.pem"),
+        );
+        let tls_config = consul.config.as_ref().unwrap().tls_config.as_ref().unwrap();
+        assert_eq!(
+            tls_config.cert_file.as_deref(),
+            Some("/etc/consul/client.pem")
+        );
+        assert_eq!(
+            tls_config.key_file.as_deref(),
+            Some("/etc/consul/client-key.pem")
+        );
+        assert_eq!(tls_config.ca_file.as_deref(), Some("/etc/consul/ca.pem"));
+        assert!(consul.http_client().is_ok());
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(req.url().scheme(), "https");
+
+        let mut consul = consul;
+        let tls_config = consul.config.as_mut().unwrap().tls_config.as_mut().unwrap();
+        tls_config.key_file = None;
+        assert!(consul.http_client().is_err());
+    }
+
+    #[test]
+    fn test_tls_server_name() {
+        let mut consul = ConsulConfig::with_tls(
+            "https://10.0.0.1:8501",
+            "/etc/consul/client.pem",
+            "/etc/consul/client-key.pem",
+            None,
+        );
+        let tls_config = consul.config.as_mut().unwrap().tls_config.as_mut().unwrap();
+        tls_config.address = Some(String::from("server.dc1.consul:8501"));
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(
+            req.url().as_str(),
+            "https://server.dc1.consul:8501/v1/health/service/web?dc=dc1"
+        );
+        assert!(consul.http_client().is_ok());
+
+        // The server name only matters for TLS.
+        let config = consul.config.as_mut().unwrap();
+        config.address = Some(String::from("http://10.0.0.1:8500"));
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(req.url().host_str(), Some("10.0.0.1"));
+
+        let config = consul.config.as_mut().unwrap();
+        config.tls_config.as_mut().unwrap().ca_path = Some(String::from("/etc/consul/ca.d"));
+        let err = consul.http_client().unwrap_err();
+        assert!(matches!(err, ConsulError::InvalidArgument(_)));
+    }
+
+    #[test]
+    fn test_load_config_errors() {
+        let dir = std::env::temp_dir();
+        let missing = dir.join("consul-rs-missing.yaml");
+        let err = block_on(ConsulConfig::load_config(missing.to_str().unwrap())).unwrap_err();
+        assert!(matches!(err, ConsulError::InvalidArgument(_)));
+
+        let invalid = dir.join(format!("consul-rs-invalid-{}.toml", std::process::id()));
+        std::fs::write(&invalid, "config = [").unwrap();
+        let res = block_on(ConsulConfig::load_config(invalid.to_str().unwrap()));
+        std::fs::remove_file(&invalid).unwrap();
+        let err = res.unwrap_err();
+        assert!(matches!(err, ConsulError::InvalidArgument(_)));
+        assert!(err.to_string().starts_with(invalid.to_str().unwrap()));
+    }
+
+    #[test]
+    fn test_unix_socket_address() {
+        assert_eq!(
+            unix_socket_path("unix:///var/run/consul.sock"),
+            Some("/var/run/consul.sock")
+        );
+        assert_eq!(unix_socket_path("http://127.0.0.1:8500"), None);
+        assert_eq!(unix_socket_path("unix://"), None);
+
+        let config = Config {
+            address: Some(String::from("unix:///var/run/consul.sock")),
+            ..Default::default()
+        };
+        let consul = ConsulConfig {
+            config: Some(config),
+            watch_services: None,
+        };
+        assert!(consul.http_client().is_ok());
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(req.url().as_str(), "http://localhost/v1/health/service/web");
+    }
+
+    pub(crate) fn mock_consul(server: &MockServer) -> ConsulConfig {
+        let config = Config {
+            address: Some(server.address.to_string()),
+            ..Default::default()
+        };
+        ConsulConfig {
+            config: Some(config),
+            watch_services: None,
+        }
+    }
+
+    #[test]
+    fn test_on_request() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, r#""10.1.10.12:8300""#),
+            MockResponse::new(403, "Permission denied"),
+        ]);
+        let seen = Arc::new(Mutex::new(vec![]));
+        let recorded = seen.clone();
+        let mut consul = mock_consul(&server);
+        consul.on_request(Box::new(move |info: &RequestInfo| {
+            recorded.lock().unwrap().push(info.clone());
+        }));
+        block_on(consul.status_leader(None)).unwrap();
+        block_on(consul.status_leader(None)).unwrap_err();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0].method, Method::Get);
+        assert_eq!(seen[0].path, "/v1/status/leader");
+        assert!(seen[0].status.unwrap().is_success());
+        assert_eq!(seen[1].status, Some(surf::StatusCode::Forbidden));
+    }
+
+    #[test]
+    fn test_health_service_meta() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, "[]")
+                .header("X-Consul-Index", "812")
+                .header("X-Consul-LastContact", "35")
+                .header("X-Consul-KnownLeader", "true")
+                .header("X-Consul-Translate-Addresses", "true")
+                .header("X-Consul-Default-ACL-Policy", "deny")
+                .header("X-Cache", "HIT")
+                .header("Age", "14"),
+            MockResponse::new(200, "[]"),
+        ]);
+        let consul = mock_consul(&server);
+        let (_, meta) = block_on(consul.health_service_meta("web", "", false, None)).unwrap();
+        assert_eq!(meta.LastIndex, 812);
+        assert_eq!(meta.LastContact, Duration::from_millis(35));
+        assert!(meta.KnownLeader);
+        assert!(meta.AddressTranslationEnabled);
+        assert_eq!(meta.DefaultACLPolicy, "deny");
+        assert!(meta.CacheHit);
+        assert_eq!(meta.CacheAge, Duration::from_secs(14));
+
+        let (_, meta) = block_on(consul.health_service_meta("web", "", false, None)).unwrap();
+        assert_eq!(meta.LastIndex, 0);
+        assert!(!meta.KnownLeader);
+        assert!(!meta.CacheHit);
+        assert_eq!(meta.DefaultACLPolicy, "");
+    }
+
+    #[test]
+    fn test_query_options_cache() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, "[]")
+                .header("X-Cache", "HIT")
+                .header("Age", "12"),
+            MockResponse::new(200, "[]").header("X-Cache", "MISS"),
+        ]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            UseCache: Some(true),
+            MaxAge: Some(Duration::from_secs(30)),
+            StaleIfError: Some(Duration::from_secs(300)),
+            ..Default::default()
+        };
+        let (_, meta) = block_on(consul.health_service_meta("web", "", false, Some(&q))).unwrap();
+        assert!(meta.CacheHit);
+        assert_eq!(meta.CacheAge, Duration::from_secs(12));
+        let (_, meta) = block_on(consul.health_service_meta("web", "", false, None)).unwrap();
+        assert!(!meta.CacheHit);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/health/service/web?cached");
+        assert_eq!(
+            requests[0].header("Cache-Control"),
+            Some("max-age=30, stale-if-error=300")
+        );
+        assert_eq!(requests[1].path, "/v1/health/service/web");
+        assert_eq!(requests[1].header("Cache-Control"), None);
+    }
+
+    #[test]
+    fn test_decode_empty_body() {
+        for body in ["", "  \n", "null", "[]"].iter() {
+            let entries: Vec<ServiceEntry> = decode_body(body.as_bytes()).unwrap();
+            assert!(entries.is_empty(), "{:?}", body);
+        }
+        let entries: Vec<ServiceEntry> =
+            decode_body(br#"[{"Service": {"ID": "web-1", "Service": "web"}}]"#).unwrap();
+        assert_eq!(entries.len(), 1);
+        let pair: Option<HashMap<String, String>> = decode_body(b"null").unwrap();
+        assert!(pair.is_none());
+        assert!(decode_body::<Vec<ServiceEntry>>(b"{").is_err());
+    }
+
+    #[test]
+    fn test_health_service_null_body() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, "null"),
+            MockResponse::new(200, ""),
+        ]);
+        let consul = mock_consul(&server);
+        let entries = block_on(consul.health_service("missing", "", false, None)).unwrap();
+        assert!(entries.is_empty());
+        let entries = block_on(consul.health_service("missing", "", false, None)).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_health_service_multi() {
+        let body = r#"[{"Service": {"ID": "web-2", "Service": "web", "Tags": ["v2", "canary"]}}]"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let entries =
+            block_on(consul.health_service_multi("web", &["v2", "canary"], true, None)).unwrap();
+        assert_eq!(entries.len(), 1);
+        let tags = entries[0].Service.as_ref().unwrap().Tags.as_ref().unwrap();
+        assert!(tags.contains(&String::from("v2")) && tags.contains(&String::from("canary")));
+
+        let path = &server.requests()[0].path;
+        let url = surf::Url::parse(&format!("http://localhost{}", path)).unwrap();
+        let tags: Vec<String> = url
+            .query_pairs()
+            .filter(|(key, _)| key == "tag")
+            .map(|(_, value)| value.into_owned())
+            .collect();
+        assert_eq!(tags, vec!["v2", "canary"]);
+        assert!(path.ends_with("&passing"));
+    }
+
+    #[test]
+    fn test_health_service_passing_flag() {
+        let server = MockServer::serve(vec![MockResponse::new(200, "[]")]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            Datacenter: Some(String::from("dc2")),
+            Consistency: Consistency::Stale,
+            WaitIndex: Some(42),
+            ..Default::default()
+        };
+        block_on(consul.health_service_multi("web", &["v2", "canary"], true, Some(&q))).unwrap();
+        assert_eq!(
+            server.requests()[0].path,
+            "/v1/health/service/web?tag=v2&tag=canary&passing&dc=dc2&stale&index=42"
+        );
+    }
+
+    #[test]
+    fn test_health_service_instance() {
+        let body = r#"[
+            {"Node": {"Node": "node-a", "Address": "10.0.0.1"},
+             "Service": {"ID": "web-1", "Service": "web", "Tags": ["stable"], "Port": 8080}},
+            {"Node": {"Node": "node-b", "Address": "10.0.0.2"},
+             "Service": {"ID": "web-canary", "Service": "web", "Tags": ["canary"], "Port": 8080}}
+        ]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body),
+            MockResponse::new(200, body),
+        ]);
+        let consul = mock_consul(&server);
+        let entry = block_on(consul.health_service_instance("web", "web-canary", None))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            entry.Node.as_ref().unwrap().Address.as_deref(),
+            Some("10.0.0.2")
+        );
+        let missing = block_on(consul.health_service_instance("web", "web-2", None)).unwrap();
+        assert!(missing.is_none());
+        assert_eq!(server.requests()[0].path, "/v1/health/service/web");
+    }
+
+    #[test]
+    fn test_health_service_statuses() {
+        let body = r#"[
+            {"Service": {"ID": "web-1", "Service": "web"},
+             "Checks": [{"CheckID": "serfHealth", "Status": "passing"},
+                        {"CheckID": "service:web-1", "Status": "passing"}]},
+            {"Service": {"ID": "web-2", "Service": "web"},
+             "Checks": [{"CheckID": "serfHealth", "Status": "passing"},
+                        {"CheckID": "service:web-2", "Status": "warning"}]},
+            {"Service": {"ID": "web-3", "Service": "web"},
+             "Checks": [{"CheckID": "serfHealth", "Status": "critical"},
+                        {"CheckID": "service:web-3", "Status": "warning"}]},
+            {"Service": {"ID": "web-4", "Service": "web"}}
+        ]"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let statuses = block_on(consul.health_service_statuses("web", "", false, None)).unwrap();
+        let statuses: Vec<(&str, HealthStatus)> = statuses
+            .iter()
+            .map(|(entry, status)| {
+                let id = entry.Service.as_ref().unwrap().ID.as_deref().unwrap();
+                (id, *status)
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("web-1", HealthStatus::Passing),
+                ("web-2", HealthStatus::Warning),
+                ("web-3", HealthStatus::Critical),
+                ("web-4", HealthStatus::Passing),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_health_service_statuses_unknown() {
+        let body = r#"[
+            {"Service": {"ID": "web-1", "Service": "web"},
+             "Checks": [{"CheckID": "service:web-1", "Status": "degraded"}]}
+        ]"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let err = block_on(consul.health_service_statuses("web", "", false, None)).unwrap_err();
+        assert!(matches!(
+            err,
+            ConsulError::Health(HealthError::UnknownStatus(ref status)) if status == "degraded"
+        ));
+    }
+
+    #[test]
+    fn test_health_service_addresses() {
+        let body = r#"[
+            {"Node": {"Node": "node-a", "Address": "10.0.0.1"},
+             "Service": {"ID": "web-1", "Service": "web", "Address": "172.17.0.3", "Port": 8080}},
+            {"Node": {"Node": "node-b", "Address": "10.0.0.2"},
+             "Service": {"ID": "web-2", "Service": "web", "Address": "", "Port": 8080}},
+            {"Node": {"Node": "node-c", "Address": "fe80::1"},
+             "Service": {"ID": "web-3", "Service": "web", "Port": 8080}},
+            {"Node": {"Node": "node-b", "Address": "10.0.0.2"},
+             "Service": {"ID": "web-4", "Service": "web", "Port": 8080}},
+            {"Node": {"Node": "node-d", "Address": ""},
+             "Service": {"ID": "web-5", "Service": "web", "Port": 8080}}
+        ]"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let addresses = block_on(consul.health_service_addresses("web", "", true, None)).unwrap();
+        assert_eq!(
+            addresses,
+            vec!["172.17.0.3:8080", "10.0.0.2:8080", "[fe80::1]:8080"]
+        );
+    }
+
+    #[test]
+    fn test_health_service_urls() {
+        let body = r#"[
+            {"Node": {"Node": "node-a", "Address": "10.0.0.1"},
+             "Service": {"ID": "web-1", "Service": "web", "Port": 8080}},
+            {"Node": {"Node": "node-b", "Address": "10.0.0.2"},
+             "Service": {"ID": "web-2", "Service": "web", "Address": "2001:db8::2", "Port": 8443}}
+        ]"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let urls = block_on(consul.health_service_urls("web", "", true, "https", None)).unwrap();
+        assert_eq!(
+            urls,
+            vec!["https://10.0.0.1:8080", "https://[2001:db8::2]:8443"]
+        );
+        let err = block_on(consul.health_service_urls("web", "", true, "", None)).unwrap_err();
+        assert!(matches!(err, ConsulError::InvalidArgument(_)));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_get_json() {
+        #[derive(Debug, Default, serde_derive::Deserialize)]
+        #[allow(non_snake_case)]
+        struct Leader {
+            Address: String,
+            Port: u16,
+        }
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, r#"{"Address": "10.1.10.12", "Port": 8300}"#),
+            MockResponse::new(403, "Permission denied"),
+        ]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            Datacenter: Some(String::from("dc2")),
+            Token: Some(String::from("secret")),
+            ..Default::default()
+        };
+        let leader: Leader = block_on(consul.get_json("/v1/custom/leader", Some(&q))).unwrap();
+        assert_eq!(leader.Address, "10.1.10.12");
+        assert_eq!(leader.Port, 8300);
+        let err = block_on(consul.get_json::<Leader>("/v1/custom/leader", None)).unwrap_err();
+        assert_eq!(err.status(), Some(surf::StatusCode::Forbidden));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/v1/custom/leader?dc=dc2");
+        assert_eq!(requests[0].header("X-Consul-Token"), Some("secret"));
+    }
+
+    #[test]
+    fn test_write_json() {
+        #[derive(serde_derive::Serialize)]
+        #[allow(non_snake_case)]
+        struct Intention {
+            SourceName: String,
+            Action: String,
+        }
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, r#"{"ID": "8f246b77"}"#),
+            MockResponse::new(200, ""),
+        ]);
+        let consul = mock_consul(&server);
+        let body = Intention {
+            SourceName: String::from("web"),
+            Action: String::from("allow"),
+        };
+        let w = WriteOptions {
+            Datacenter: Some(String::from("dc2")),
+            Token: Some(String::from("secret")),
+            ..Default::default()
+        };
+        let (out, _): (serde_json::Value, _) =
+            block_on(consul.write_json(Method::Post, "/v1/custom/intention", &body, Some(&w)))
+                .unwrap();
+        assert_eq!(out["ID"], "8f246b77");
+        let (out, _): ((), _) =
+            block_on(consul.write_json(Method::Put, "/v1/custom/intention", &body, None)).unwrap();
+        assert_eq!(out, ());
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/v1/custom/intention?dc=dc2");
+        assert_eq!(requests[0].header("X-Consul-Token"), Some("secret"));
+        let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(sent["SourceName"], "web");
+        assert_eq!(sent["Action"], "allow");
+        assert_eq!(requests[1].header("X-Consul-Token"), None);
+    }
+
+    #[test]
+    fn test_health_service_not_found() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(404, ""),
+            MockResponse::new(400, "Bad request"),
+        ]);
+        let consul = mock_consul(&server);
+        let entries = block_on(consul.health_service("missing", "", false, None)).unwrap();
+        assert!(entries.is_empty());
+        let err = block_on(consul.health_service("missing", "", false, None)).unwrap_err();
+        assert_eq!(err.status(), Some(surf::StatusCode::BadRequest));
+    }
+
+    #[test]
+    fn test_watch_service() {
+        use futures_util::StreamExt;
+        let one = r#"[{"Service": {"ID": "web-1", "Service": "web", "Address": "10.0.0.1", "Port": 8080}}]"#;
+        let two = r#"[
+            {"Service": {"ID": "web-1", "Service": "web", "Address": "10.0.0.1", "Port": 8080}},
+            {"Service": {"ID": "web-2", "Service": "web", "Address": "10.0.0.2", "Port": 8080}}
+        ]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, one).header("X-Consul-Index", "10"),
+            MockResponse::new(200, ""),
+            MockResponse::new(200, one).header("X-Consul-Index", "10"),
+            MockResponse::new(200, two).header("X-Consul-Index", "12"),
+            MockResponse::new(200, one).header("X-Consul-Index", "3"),
+            MockResponse::new(200, one).header("X-Consul-Index", "3"),
+        ]);
+        let consul = mock_consul(&server);
+        let service = AgentServiceRegistration {
+            ID: Some(String::from("web-2")),
+            Name: Some(String::from("web")),
+            Port: Some(8080),
+            ..Default::default()
+        };
+        let watch = consul.watch_service("web", "", true, None);
+        futures_util::pin_mut!(watch);
+        let entries = block_on(watch.next()).unwrap().unwrap();
+        assert_eq!(entries.len(), 1);
+        block_on(consul.register_service(&service, None)).unwrap();
+        let entries = block_on(watch.next()).unwrap().unwrap();
+        assert_eq!(entries.len(), 2);
+        let entries = block_on(watch.next()).unwrap().unwrap();
+        assert_eq!(entries.len(), 1);
+
+        let indexes: Vec<String> = server
+            .requests()
+            .iter()
+            .filter(|req| req.method == "GET")
+            .map(|req| {
+                let url = surf::Url::parse(&format!("http://localhost{}", req.path)).unwrap();
+                assert_eq!(url.path(), "/v1/health/service/web");
+                url.query_pairs()
+                    .find(|(key, _)| key == "index")
+                    .map(|(_, value)| value.into_owned())
+                    .unwrap()
+            })
+            .collect();
+        // The request for index 12 comes back at 3, so the watch restarts from 0.
+        assert_eq!(indexes, vec!["0", "10", "10", "12", "0"]);
+    }
+
+    #[test]
+    fn test_watch_service_backoff() {
+        use futures_util::StreamExt;
+        let one = r#"[{"Service": {"ID": "web-1", "Service": "web", "Port": 8080}}]"#;
+        let two = r#"[
+            {"Service": {"ID": "web-1", "Service": "web", "Port": 8080}},
+            {"Service": {"ID": "web-2", "Service": "web", "Port": 8080}}
+        ]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(403, "Permission denied"),
+            MockResponse::new(200, one),
+            MockResponse::new(200, one),
+            MockResponse::new(200, two).header("X-Consul-Index", "5"),
+        ]);
+        let mut consul = mock_consul(&server);
+        consul.config.as_mut().unwrap().retry = Some(RetryPolicy {
+            max_attempts: 3,
+            base_backoff: Duration::from_millis(200),
+        });
+        let q = QueryOptions {
+            Datacenter: Some(String::from("dc2")),
+            Token: Some(String::from("secret")),
+            Filter: Some(String::from("Service.Port == 8080")),
+            ..Default::default()
+        };
+        let watch = consul.watch_service("web", "", false, Some(&q));
+        futures_util::pin_mut!(watch);
+        assert!(block_on(watch.next()).unwrap().is_err());
+        let start = std::time::Instant::now();
+        let entries = block_on(watch.next()).unwrap().unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(entries.len(), 1);
+        let entries = block_on(watch.next()).unwrap().unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let requests = server.requests();
+        let indexes: Vec<String> = requests
+            .iter()
+            .map(|req| {
+                assert_eq!(req.header("X-Consul-Token"), Some("secret"));
+                let url = surf::Url::parse(&format!("http://localhost{}", req.path)).unwrap();
+                let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+                assert_eq!(query["dc"], "dc2");
+                assert_eq!(query["filter"], "Service.Port == 8080");
+                query["index"].clone()
+            })
+            .collect();
+        // Without an X-Consul-Index the index is clamped to 1 rather than 0.
+        assert_eq!(indexes, vec!["0", "0", "1", "1"]);
+    }
+
+    #[test]
+    fn test_retry_reads() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(500, "rpc error"),
+            MockResponse::new(503, "no leader"),
+            MockResponse::new(200, "[]"),
+        ]);
+        let consul = mock_consul(&server);
+        let entries = block_on(consul.health_service("web", "", false, None)).unwrap();
+        assert!(entries.is_empty());
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|req| req.method == "GET"));
+        assert!(requests[2].path.starts_with("/v1/health/service/web"));
+    }
+
+    #[test]
+    fn test_no_retry_writes() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(500, "rpc error"),
+            MockResponse::new(200, ""),
+        ]);
+        let consul = mock_consul(&server);
+        let status = block_on(consul.service_deregister(String::from("web-1"))).unwrap();
+        assert_eq!(status, surf::StatusCode::InternalServerError);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "PUT");
+    }
+
+    #[test]
+    fn test_builder() {
+        let consul = ConsulConfig::builder().build();
+        let config = consul.config.unwrap();
+        assert_eq!(config.address.as_deref(), Some("http://127.0.0.1:8500"));
+        assert_eq!(config.datacenter.as_deref(), Some("dc1"));
+        assert!(config.token.is_none());
+        assert!(config.timeout.is_none());
+
+        let consul = ConsulConfig::builder()
+            .address("https://consul.dc2.example.com:8501")
+            .datacenter("dc2")
+            .namespace("team-a")
+            .token("secret")
+            .timeout(std::time::Duration::from_secs(5))
+            .tls_config(TLSConfig {
+                insecure_skip_verify: Some(true),
+                ..Default::default()
+            })
+            .build();
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(
+            req.url().as_str(),
+            "https://consul.dc2.example.com:8501/v1/health/service/web?dc=dc2&ns=team-a"
+        );
+        assert_eq!(req.header(HTTP_TOKEN_HEADER).unwrap().last(), "secret");
+        let config = consul.config.as_ref().unwrap();
+        assert_eq!(config.timeout, Some(std::time::Duration::from_secs(5)));
+        assert!(consul.http_client().is_ok());
+    }
+
+    #[test]
+    fn test_query_options_consistency() {
+        let consul = ConsulConfig::default();
+        let query = |consistency| {
+            let q = QueryOptions {
+                Consistency: consistency,
+                ..Default::default()
+            };
+            let req = block_on(consul.health_service_request("web", "", false, Some(&q))).unwrap();
+            req.url().query().map(String::from)
+        };
+        assert_eq!(query(Consistency::Default).as_deref(), Some("dc=dc1"));
+        assert_eq!(query(Consistency::Stale).as_deref(), Some("dc=dc1&stale"));
+        assert_eq!(
+            query(Consistency::Consistent).as_deref(),
+            Some("dc=dc1&consistent")
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_query_options_deprecated_consistency() {
+        let consul = ConsulConfig::default();
+        let query = |q: QueryOptions| {
+            let req = block_on(consul.health_service_request("web", "", false, Some(&q))).unwrap();
+            req.url().query().map(String::from)
+        };
+        let stale = QueryOptions {
+            AllowStale: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(query(stale.clone()).as_deref(), Some("dc=dc1&stale"));
+        let consistent = QueryOptions {
+            RequireConsistent: Some(true),
+            ..stale.clone()
+        };
+        assert_eq!(query(consistent).as_deref(), Some("dc=dc1&consistent"));
+        // An explicit Consistency wins over the deprecated fields.
+        let explicit = QueryOptions {
+            Consistency: Consistency::Consistent,
+            ..stale
+        };
+        assert_eq!(query(explicit).as_deref(), Some("dc=dc1&consistent"));
+    }
+
+    #[test]
+    fn test_global_config_in_async_context() {
+        use crate::api::CONSUL_CONFIG;
+        let _global = lock_global();
+        // The global is built without blocking, so the first access may happen
+        // inside the runtime.
+        let configured = block_on(async {
+            let consul = CONSUL_CONFIG.read().await;
+            consul.config.is_some()
+        });
+        assert!(configured);
+    }
+
+    #[test]
+    fn test_reload_with() {
+        use crate::api::CONSUL_CONFIG;
+        let _global = lock_global();
+        let first = MockServer::serve(vec![MockResponse::new(200, r#""10.0.1.1:8300""#)]);
+        let second = MockServer::serve(vec![MockResponse::new(200, r#""10.0.2.1:8300""#)]);
+        let leader = || {
+            block_on(async {
+                let consul = CONSUL_CONFIG.read().await;
+                consul.status_leader(None).await.unwrap()
+            })
+        };
+        for (server, datacenter) in [(&first, "dc1"), (&second, "dc2")].iter() {
+            let config = Config {
+                address: Some(server.address.to_string()),
+                datacenter: Some(datacenter.to_string()),
+                token: Some(String::from("secret")),
+                ..Default::default()
+            };
+            block_on(ConsulConfig::reload_with(config));
+            leader();
+        }
+        let first = first.requests();
+        let second = second.requests();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].path, "/v1/status/leader?dc=dc1");
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].path, "/v1/status/leader?dc=dc2");
+        assert_eq!(second[0].header("X-Consul-Token"), Some("secret"));
+    }
+
+    #[test]
+    fn test_from_env() {
+        let _env = ENV_LOCK.lock().unwrap();
+        std::env::remove_var(HTTP_ADDR_ENV_NAME);
+        std::env::remove_var(HTTP_SSL_ENV_NAME);
+        std::env::remove_var(HTTP_TOKEN_ENV_NAME);
+        let consul = ConsulConfig::from_env();
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(
+            req.url().as_str(),
+            "http://127.0.0.1:8500/v1/health/service/web?dc=dc1"
+        );
+        assert!(req.header(HTTP_TOKEN_HEADER).is_none());
+
+        std::env::set_var(HTTP_ADDR_ENV_NAME, "consul.example.com:8501");
+        std::env::set_var(HTTP_SSL_ENV_NAME, "true");
+        std::env::set_var(HTTP_TOKEN_ENV_NAME, "env-token");
+        let consul = ConsulConfig::from_env();
+        std::env::remove_var(HTTP_ADDR_ENV_NAME);
+        std::env::remove_var(HTTP_SSL_ENV_NAME);
+        std::env::remove_var(HTTP_TOKEN_ENV_NAME);
+        let config = consul.config.as_ref().unwrap();
+        assert_eq!(config.address.as_deref(), Some("consul.example.com:8501"));
+        assert_eq!(config.scheme.as_deref(), Some("https"));
+        assert_eq!(config.token.as_deref(), Some("env-token"));
+        let req = block_on(consul.health_service_request("web", "", false, None)).unwrap();
+        assert_eq!(
+            req.url().as_str(),
+            "https://consul.example.com:8501/v1/health/service/web?dc=dc1"
+        );
+    }
 }
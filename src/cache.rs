@@ -0,0 +1,99 @@
+use super::api::{service_address, ConsulConfig};
+use super::error::ConsulError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+type CacheKey = (String, String, bool);
+
+/// ServiceCache memoizes the addresses of a service for a TTL so hot lookup
+/// paths do not query the agent every time. Entries past the TTL are
+/// refreshed lazily by the next `get`.
+#[derive(Debug)]
+pub struct ServiceCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, (Instant, Vec<String>)>>,
+}
+
+impl ServiceCache {
+    pub fn new(ttl: Duration) -> Self {
+        ServiceCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// get returns the `host:port` addresses of a service, from the cache
+    /// while they are younger than the TTL and from the agent otherwise.
+    pub async fn get(
+        &self,
+        consul: &ConsulConfig,
+        service: &str,
+        tag: &str,
+        passing_only: bool,
+    ) -> Result<Vec<String>, ConsulError> {
+        let key = (service.to_string(), tag.to_string(), passing_only);
+        if let Some((fetched, addresses)) = self.entries.lock().unwrap().get(&key) {
+            if fetched.elapsed() < self.ttl {
+                return Ok(addresses.clone());
+            }
+        }
+        let entries = consul
+            .health_service(service, tag, passing_only, None)
+            .await?;
+        let (_, addresses) = service_address(&entries, 0, false);
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), addresses.clone()));
+        Ok(addresses)
+    }
+
+    /// invalidate drops the cached addresses of a service so the next `get`
+    /// asks the agent again.
+    pub fn invalidate(&self, service: &str, tag: &str, passing_only: bool) {
+        let key = (service.to_string(), tag.to_string(), passing_only);
+        self.entries.lock().unwrap().remove(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::cache::ServiceCache;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_service_cache() {
+        let one = r#"[{"Service": {"ID": "web-1", "Address": "10.0.0.1", "Port": 8080, "ModifyIndex": 7}}]"#;
+        let two = r#"[
+            {"Service": {"ID": "web-1", "Address": "10.0.0.1", "Port": 8080, "ModifyIndex": 7}},
+            {"Service": {"ID": "web-2", "Address": "10.0.0.2", "Port": 8080, "ModifyIndex": 7}}
+        ]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, one),
+            MockResponse::new(200, two),
+            MockResponse::new(200, one),
+        ]);
+        let consul = mock_consul(&server);
+        let cache = ServiceCache::new(Duration::from_millis(200));
+        let addresses = block_on(cache.get(&consul, "web", "", true)).unwrap();
+        assert_eq!(addresses, vec!["10.0.0.1:8080"]);
+        let addresses = block_on(cache.get(&consul, "web", "", true)).unwrap();
+        assert_eq!(addresses, vec!["10.0.0.1:8080"]);
+        assert_eq!(server.requests().len(), 1);
+
+        thread::sleep(Duration::from_millis(250));
+        let addresses = block_on(cache.get(&consul, "web", "", true)).unwrap();
+        assert_eq!(addresses.len(), 2);
+        assert_eq!(server.requests().len(), 2);
+
+        cache.invalidate("web", "", true);
+        let addresses = block_on(cache.get(&consul, "web", "", true)).unwrap();
+        assert_eq!(addresses.len(), 1);
+        assert_eq!(server.requests().len(), 3);
+    }
+}
@@ -1,5 +1,13 @@
+use super::agent::{AgentCheck, AgentService};
+use super::api::{
+    append_query, decode_json, require_ok, ConsulConfig, QueryMeta, QueryOptions, WriteOptions,
+};
+use super::error::ConsulError;
+use super::health::HealthChecks;
+use serde::de::IgnoredAny;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
+use surf::http::Method;
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[allow(non_snake_case)]
@@ -20,3 +28,492 @@ pub struct ServiceAddress {
     pub Address: Option<String>,
     pub Port: Option<usize>,
 }
+
+/// CatalogService is a catalog registration of a service instance, as
+/// returned without regard to its health checks.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CatalogService {
+    pub ID: Option<String>,
+    pub Node: Option<String>,
+    pub Address: Option<String>,
+    pub Datacenter: Option<String>,
+    pub TaggedAddresses: Option<HashMap<String, String>>,
+    pub NodeMeta: Option<HashMap<String, String>>,
+    pub ServiceID: Option<String>,
+    pub ServiceName: Option<String>,
+    pub ServiceAddress: Option<String>,
+    pub ServiceTags: Option<Vec<String>>,
+    pub ServiceMeta: Option<HashMap<String, String>>,
+    pub ServicePort: Option<usize>,
+    pub ServiceEnableTagOverride: Option<bool>,
+    pub Namespace: Option<String>,
+    pub CreateIndex: Option<u64>,
+    pub ModifyIndex: Option<u64>,
+}
+
+/// CatalogNode is a node together with every service registered on it.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CatalogNode {
+    pub Node: Option<Node>,
+    pub Services: Option<HashMap<String, AgentService>>,
+}
+
+/// CatalogRegistration is used to register a node, and optionally a service
+/// and checks on it, directly in the catalog.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CatalogRegistration {
+    pub ID: Option<String>,
+    pub Node: Option<String>,
+    pub Address: Option<String>,
+    pub TaggedAddresses: Option<HashMap<String, String>>,
+    pub NodeMeta: Option<HashMap<String, String>>,
+    pub Datacenter: Option<String>,
+    pub Service: Option<AgentService>,
+    pub Check: Option<AgentCheck>,
+    pub Checks: Option<HealthChecks>,
+    pub SkipNodeUpdate: Option<bool>,
+}
+
+/// CatalogDeregistration is used to remove a node, or a service or check on
+/// it, from the catalog.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CatalogDeregistration {
+    pub Node: Option<String>,
+    pub Datacenter: Option<String>,
+    pub ServiceID: Option<String>,
+    pub CheckID: Option<String>,
+    pub Namespace: Option<String>,
+}
+
+/// CompoundServiceName is a service name qualified by its namespace.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CompoundServiceName {
+    pub Name: Option<String>,
+
+    /// Namespacing is a Consul Enterprise feature.
+    pub Namespace: Option<String>,
+}
+
+/// GatewayService associates a gateway with a linked service.
+/// It also contains service-specific gateway configuration like ingress listener port and protocol.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct GatewayService {
+    pub Gateway: Option<CompoundServiceName>,
+    pub Service: Option<CompoundServiceName>,
+    pub GatewayKind: Option<String>,
+    pub Port: Option<usize>,
+    pub Protocol: Option<String>,
+    pub Hosts: Option<Vec<String>>,
+    pub CAFile: Option<String>,
+    pub CertFile: Option<String>,
+    pub KeyFile: Option<String>,
+    pub SNI: Option<String>,
+    pub FromWildcard: Option<bool>,
+}
+
+impl ConsulConfig {
+    /// catalog_services is used to query for all known services, returning
+    /// the tags registered for each of them.
+    pub async fn catalog_services(
+        &self,
+        q: Option<&QueryOptions>,
+    ) -> Result<HashMap<String, Vec<String>>, ConsulError> {
+        self.get_json("/v1/catalog/services", q).await
+    }
+
+    /// catalog_services_meta is like catalog_services but also returns the
+    /// QueryMeta, so callers can pass `LastIndex` back as `WaitIndex` to
+    /// block until the service list changes instead of refetching it.
+    pub async fn catalog_services_meta(
+        &self,
+        q: Option<&QueryOptions>,
+    ) -> Result<(HashMap<String, Vec<String>>, QueryMeta), ConsulError> {
+        self.get_json_meta("/v1/catalog/services", q).await
+    }
+
+    /// catalog_nodes is used to query all the known nodes. Set `Near` in the
+    /// query options to sort them by round trip time.
+    pub async fn catalog_nodes(&self, q: Option<&QueryOptions>) -> Result<Vec<Node>, ConsulError> {
+        self.get_json("/v1/catalog/nodes", q).await
+    }
+
+    /// catalog_service is used to query catalog entries for a given service.
+    /// An empty tag matches every instance.
+    pub async fn catalog_service(
+        &self,
+        service: &str,
+        tag: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<CatalogService>, ConsulError> {
+        let tags = if tag.is_empty() {
+            &[]
+        } else {
+            std::slice::from_ref(&tag)
+        };
+        self.catalog_service_with_meta(service, tags, &[], q).await
+    }
+
+    /// catalog_service_with_meta is like catalog_service but matches every
+    /// given tag and node metadata pair at once. Empty slices add no filter.
+    pub async fn catalog_service_with_meta(
+        &self,
+        service: &str,
+        tags: &[&str],
+        node_meta: &[(&str, &str)],
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<CatalogService>, ConsulError> {
+        let path = format!("/v1/catalog/service/{}", service);
+        let mut req = self.new_request(Method::Get, &path).await?;
+        for tag in tags.iter().filter(|tag| !tag.is_empty()) {
+            append_query(&mut req, "tag", tag);
+        }
+        for (key, value) in node_meta.iter() {
+            append_query(&mut req, "node-meta", &format!("{}:{}", key, value));
+        }
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        let mut res = require_ok(self.send(req).await?).await?;
+        let out: Vec<CatalogService> = decode_json(&mut res).await?;
+        Ok(out)
+    }
+
+    /// catalog_node_services is used to query for the services provided by a
+    /// node.
+    pub async fn catalog_node_services(
+        &self,
+        node: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<CatalogNode, ConsulError> {
+        let path = format!("/v1/catalog/node/{}", node);
+        self.get_json(&path, q).await
+    }
+
+    /// catalog_datacenters is used to query for all the known datacenters.
+    pub async fn catalog_datacenters(&self) -> Result<Vec<String>, ConsulError> {
+        self.get_json("/v1/catalog/datacenters", None).await
+    }
+
+    /// catalog_gateway_services lists the services associated with an
+    /// ingress or terminating gateway.
+    pub async fn catalog_gateway_services(
+        &self,
+        gateway: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<GatewayService>, ConsulError> {
+        let path = format!("/v1/catalog/gateway-services/{}", gateway);
+        self.get_json(&path, q).await
+    }
+
+    /// catalog_register is used to register an entity directly in the catalog,
+    /// bypassing the agent's anti-entropy sync.
+    pub async fn catalog_register(
+        &self,
+        reg: &CatalogRegistration,
+        q: Option<&WriteOptions>,
+    ) -> Result<(), ConsulError> {
+        self.write_json::<_, IgnoredAny>(Method::Put, "/v1/catalog/register", reg, q)
+            .await?;
+        Ok(())
+    }
+
+    /// catalog_deregister is used to remove an entity from the catalog.
+    pub async fn catalog_deregister(
+        &self,
+        dereg: &CatalogDeregistration,
+        q: Option<&WriteOptions>,
+    ) -> Result<(), ConsulError> {
+        self.write_json::<_, IgnoredAny>(Method::Put, "/v1/catalog/deregister", dereg, q)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::agent::{AgentService, AgentServiceRegistration};
+    use crate::api::tests::mock_consul;
+    use crate::api::{QueryOptions, WriteOptions};
+    use crate::catalog::{CatalogDeregistration, CatalogRegistration};
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn query_pairs(path: &str) -> Vec<(String, String)> {
+        let url = surf::Url::parse(&format!("http://localhost{}", path)).unwrap();
+        url.query_pairs().into_owned().collect()
+    }
+
+    #[test]
+    fn test_catalog_services() {
+        let body = r#"{"consul": [], "web": ["v1", "prod"]}"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            Datacenter: Some(String::from("dc2")),
+            Filter: Some(String::from("ServiceMeta.env == prod")),
+            ..Default::default()
+        };
+        let services = block_on(consul.catalog_services(Some(&q))).unwrap();
+        assert_eq!(services["web"], vec!["v1", "prod"]);
+        assert!(services["consul"].is_empty());
+
+        let requests = server.requests();
+        assert!(requests[0].path.starts_with("/v1/catalog/services?"));
+        let query = query_pairs(&requests[0].path);
+        assert!(query.contains(&(String::from("dc"), String::from("dc2"))));
+        assert!(query.contains(&(
+            String::from("filter"),
+            String::from("ServiceMeta.env == prod")
+        )));
+    }
+
+    #[test]
+    fn test_catalog_services_meta() {
+        let body = r#"{"consul": [], "web": ["v1"]}"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body).header("X-Consul-Index", "4021")
+        ]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            WaitIndex: Some(4000),
+            WaitTime: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+        let (services, meta) = block_on(consul.catalog_services_meta(Some(&q))).unwrap();
+        assert_eq!(services["web"], vec!["v1"]);
+        assert_eq!(meta.LastIndex, 4021);
+
+        let query = query_pairs(&server.requests()[0].path);
+        assert!(query.contains(&(String::from("index"), String::from("4000"))));
+        assert!(query.contains(&(String::from("wait"), String::from("10000ms"))));
+    }
+
+    #[test]
+    fn test_catalog_nodes() {
+        let body = r#"[
+            {"ID": "40e4a748", "Node": "node-a", "Address": "10.1.10.11", "Datacenter": "dc1"},
+            {"ID": "40e4a749", "Node": "node-b", "Address": "10.1.10.12", "Datacenter": "dc1"}
+        ]"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let mut node_meta = HashMap::new();
+        node_meta.insert(String::from("rack"), String::from("r1"));
+        node_meta.insert(String::from("env"), String::from("prod"));
+        let q = QueryOptions {
+            Near: Some(String::from("_agent")),
+            NodeMeta: Some(node_meta),
+            ..Default::default()
+        };
+        let nodes = block_on(consul.catalog_nodes(Some(&q))).unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[1].Node.as_deref(), Some("node-b"));
+        assert_eq!(nodes[1].Address.as_deref(), Some("10.1.10.12"));
+
+        let requests = server.requests();
+        assert!(requests[0].path.starts_with("/v1/catalog/nodes?"));
+        let query = query_pairs(&requests[0].path);
+        assert!(query.contains(&(String::from("near"), String::from("_agent"))));
+        let node_meta: Vec<&str> = query
+            .iter()
+            .filter(|(key, _)| key == "node-meta")
+            .map(|(_, value)| value.as_str())
+            .collect();
+        assert_eq!(node_meta, vec!["env:prod", "rack:r1"]);
+    }
+
+    #[test]
+    fn test_catalog_service() {
+        let body = r#"[{
+            "Node": "node-a",
+            "Address": "10.1.10.11",
+            "ServiceID": "web-1",
+            "ServiceName": "web",
+            "ServiceAddress": "172.17.0.3",
+            "ServicePort": 8080,
+            "ServiceTags": ["v1"]
+        }]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, ""),
+            MockResponse::new(200, body),
+        ]);
+        let consul = mock_consul(&server);
+        let service = AgentServiceRegistration {
+            ID: Some(String::from("web-1")),
+            Name: Some(String::from("web")),
+            Address: Some(String::from("172.17.0.3")),
+            Port: Some(8080),
+            Tags: Some(vec![String::from("v1")]),
+            ..Default::default()
+        };
+        block_on(consul.register_service(&service, None)).unwrap();
+        let services = block_on(consul.catalog_service("web", "v1", None)).unwrap();
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].Node.as_deref(), Some("node-a"));
+        assert_eq!(services[0].Address.as_deref(), Some("10.1.10.11"));
+        assert_eq!(services[0].ServiceID.as_deref(), Some("web-1"));
+        assert_eq!(services[0].ServiceAddress.as_deref(), Some("172.17.0.3"));
+        assert_eq!(services[0].ServicePort, Some(8080));
+        assert_eq!(services[0].ServiceTags, Some(vec![String::from("v1")]));
+
+        let requests = server.requests();
+        assert!(requests[1].path.starts_with("/v1/catalog/service/web?"));
+        let query = query_pairs(&requests[1].path);
+        assert!(query.contains(&(String::from("tag"), String::from("v1"))));
+    }
+
+    #[test]
+    fn test_catalog_register() {
+        let body = r#"[{"Node": "external", "Address": "192.0.2.10", "ServiceID": "redis-1"}]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, "true"),
+            MockResponse::new(200, body),
+            MockResponse::new(200, "true"),
+            MockResponse::new(200, "[]"),
+        ]);
+        let consul = mock_consul(&server);
+        let reg = CatalogRegistration {
+            Node: Some(String::from("external")),
+            Address: Some(String::from("192.0.2.10")),
+            Datacenter: Some(String::from("dc1")),
+            Service: Some(AgentService {
+                ID: Some(String::from("redis-1")),
+                Service: Some(String::from("redis")),
+                Port: Some(6379),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let q = WriteOptions {
+            Token: Some(String::from("secret")),
+            ..Default::default()
+        };
+        block_on(consul.catalog_register(&reg, Some(&q))).unwrap();
+        let services = block_on(consul.catalog_service("redis", "", None)).unwrap();
+        assert_eq!(services[0].Node.as_deref(), Some("external"));
+
+        let dereg = CatalogDeregistration {
+            Node: Some(String::from("external")),
+            ServiceID: Some(String::from("redis-1")),
+            ..Default::default()
+        };
+        block_on(consul.catalog_deregister(&dereg, Some(&q))).unwrap();
+        let services = block_on(consul.catalog_service("redis", "", None)).unwrap();
+        assert!(services.is_empty());
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert!(requests[0].path.starts_with("/v1/catalog/register"));
+        assert_eq!(requests[0].header("X-Consul-Token"), Some("secret"));
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["Node"], "external");
+        assert_eq!(body["Service"]["Port"], 6379);
+        assert_eq!(requests[2].method, "PUT");
+        assert!(requests[2].path.starts_with("/v1/catalog/deregister"));
+        let body: Value = serde_json::from_slice(&requests[2].body).unwrap();
+        assert_eq!(body["ServiceID"], "redis-1");
+    }
+
+    #[test]
+    fn test_catalog_datacenters() {
+        let server = MockServer::serve(vec![MockResponse::new(200, r#"["dc1", "dc2"]"#)]);
+        let consul = mock_consul(&server);
+        let datacenters = block_on(consul.catalog_datacenters()).unwrap();
+        assert!(datacenters.contains(&String::from("dc1")));
+        assert_eq!(server.requests()[0].path, "/v1/catalog/datacenters");
+    }
+
+    #[test]
+    fn test_catalog_node_services() {
+        let body = r#"{
+            "Node": {"ID": "40e4a748", "Node": "node-a", "Address": "10.1.10.11"},
+            "Services": {
+                "consul": {"ID": "consul", "Service": "consul", "Port": 8300},
+                "web-1": {"ID": "web-1", "Service": "web", "Tags": ["v1"], "Port": 8080}
+            }
+        }"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let node = block_on(consul.catalog_node_services("node-a", None)).unwrap();
+        assert_eq!(node.Node.unwrap().Address.as_deref(), Some("10.1.10.11"));
+        let services = node.Services.unwrap();
+        assert_eq!(services.len(), 2);
+        assert_eq!(services["web-1"].Service.as_deref(), Some("web"));
+        assert_eq!(services["web-1"].Port, Some(8080));
+        assert!(server.requests()[0]
+            .path
+            .starts_with("/v1/catalog/node/node-a"));
+    }
+
+    #[test]
+    fn test_catalog_service_with_meta() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, "[]"),
+            MockResponse::new(200, "[]"),
+        ]);
+        let consul = mock_consul(&server);
+        block_on(consul.catalog_service_with_meta(
+            "web",
+            &["prod", "v2"],
+            &[("rack", "r1"), ("zone", "a")],
+            None,
+        ))
+        .unwrap();
+        block_on(consul.catalog_service_with_meta("web", &[], &[], None)).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            query_pairs(&requests[0].path),
+            vec![
+                (String::from("tag"), String::from("prod")),
+                (String::from("tag"), String::from("v2")),
+                (String::from("node-meta"), String::from("rack:r1")),
+                (String::from("node-meta"), String::from("zone:a")),
+            ]
+        );
+        assert_eq!(requests[1].path, "/v1/catalog/service/web");
+    }
+
+    #[test]
+    fn test_catalog_gateway_services() {
+        let body = r#"[
+            {
+                "Gateway": {"Name": "ingress"},
+                "Service": {"Name": "api"},
+                "GatewayKind": "ingress-gateway",
+                "Port": 8080,
+                "Protocol": "http",
+                "Hosts": ["api.example.com"]
+            },
+            {
+                "Gateway": {"Name": "ingress"},
+                "Service": {"Name": "web"},
+                "GatewayKind": "ingress-gateway",
+                "Port": 8081,
+                "Protocol": "tcp",
+                "FromWildcard": true
+            }
+        ]"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let services = block_on(consul.catalog_gateway_services("ingress", None)).unwrap();
+        assert_eq!(services.len(), 2);
+        let api = &services[0];
+        assert_eq!(api.Service.as_ref().unwrap().Name.as_deref(), Some("api"));
+        assert_eq!(api.GatewayKind.as_deref(), Some("ingress-gateway"));
+        assert_eq!(api.Port, Some(8080));
+        assert_eq!(api.Hosts.as_ref().unwrap(), &vec!["api.example.com"]);
+        assert_eq!(services[1].FromWildcard, Some(true));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/catalog/gateway-services/ingress");
+    }
+}
@@ -52,6 +52,19 @@ lazy_static!(
     };
 );
 
+/// TransparentProxyConfig configures the proxy when it runs in transparent mode.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct TransparentProxyConfig {
+    /// The port of the listener where outbound application traffic is being redirected to.
+    pub OutboundListenerPort: Option<usize>,
+
+    /// DialedDirectly indicates whether transparent proxies can dial this proxy instance directly.
+    /// The discovery chain is not considered when dialing a service instance directly.
+    /// This setting is useful when addressing stateful services, such as a database cluster with a leader node.
+    pub DialedDirectly: Option<bool>,
+}
+
 /// MeshGatewayConfig controls how Mesh Gateways are used for upstream Connect services
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[allow(non_snake_case)]
@@ -0,0 +1,76 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// CARootList is the structure for the results of listing roots.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CARootList {
+    pub ActiveRootID: Option<String>,
+    pub TrustDomain: Option<String>,
+    pub Roots: Option<Vec<CARoot>>,
+}
+
+impl CARootList {
+    /// active returns the root currently used to sign leaf certificates.
+    pub fn active(&self) -> Option<&CARoot> {
+        self.Roots
+            .as_ref()?
+            .iter()
+            .find(|root| root.Active == Some(true))
+    }
+}
+
+/// LeafCert is a certificate that has been issued by a Connect CA.
+///
+/// Leaf certificates are short-lived. Callers holding one should fetch a
+/// fresh certificate before `ValidBefore` passes.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct LeafCert {
+    /// SerialNumber is the unique serial number for this certificate.
+    /// This is encoded in standard hex separated by :.
+    pub SerialNumber: Option<String>,
+
+    /// CertPEM and PrivateKeyPEM are the PEM-encoded certificate and private
+    /// key for that cert, respectively. This should not be stored in the
+    /// state store, but is present in the sign API response.
+    pub CertPEM: Option<String>,
+    pub PrivateKeyPEM: Option<String>,
+
+    /// Service is the name of the service for which the cert was issued.
+    /// ServiceURI is the cert URI value.
+    pub Service: Option<String>,
+    pub ServiceURI: Option<String>,
+
+    /// ValidAfter and ValidBefore are the validity periods for the
+    /// certificate, as RFC 3339 timestamps.
+    pub ValidAfter: Option<String>,
+    pub ValidBefore: Option<String>,
+
+    pub CreateIndex: Option<u64>,
+    pub ModifyIndex: Option<u64>,
+}
+
+/// CARoot represents a root CA certificate that is trusted.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CARoot {
+    /// ID is a globally unique ID (UUID) representing this CA root.
+    pub ID: Option<String>,
+
+    /// Name is a human-friendly name for this CA root. This value is
+    /// opaque to Consul and is not used for anything internally.
+    pub Name: Option<String>,
+
+    /// RootCertPEM is the PEM-formatted public certificate.
+    #[serde(rename = "RootCert")]
+    pub RootCertPEM: Option<String>,
+
+    /// Active is true if this is the current active CA. This must only
+    /// be true for exactly one CA. For any method that modifies roots in the
+    /// state store, tests should be written to verify that multiple roots
+    /// cannot be active.
+    pub Active: Option<bool>,
+
+    pub CreateIndex: Option<u64>,
+    pub ModifyIndex: Option<u64>,
+}
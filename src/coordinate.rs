@@ -0,0 +1,227 @@
+use super::api::{ConsulConfig, QueryOptions};
+use super::error::ConsulError;
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Coord is a specialized structure for holding network coordinates for the
+/// Vivaldi-based coordinate mapping algorithm. All of the fields are in
+/// seconds.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct Coord {
+    /// Vec is the Euclidean portion of the coordinate.
+    #[serde(default)]
+    pub Vec: Vec<f64>,
+
+    /// Err reflects the confidence in the given coordinate and is updated
+    /// dynamically by the Vivaldi Client.
+    #[serde(default)]
+    pub Error: f64,
+
+    /// Adjustment is a distance offset computed based on a calculation over
+    /// observations from all other nodes over a fixed window and is updated
+    /// dynamically by the Vivaldi Client.
+    #[serde(default)]
+    pub Adjustment: f64,
+
+    /// Height is a distance offset that accounts for non-Euclidean effects
+    /// which model the access links from nodes to the core Internet.
+    #[serde(default)]
+    pub Height: f64,
+}
+
+impl Coord {
+    /// distance_to returns the estimated round trip time between two
+    /// coordinates. It returns `None` when the coordinates have different
+    /// dimensions, as they were then not computed by the same cluster.
+    pub fn distance_to(&self, other: &Coord) -> Option<Duration> {
+        if self.Vec.len() != other.Vec.len() {
+            return None;
+        }
+        let magnitude = self
+            .Vec
+            .iter()
+            .zip(other.Vec.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f64>()
+            .sqrt();
+        let dist = magnitude + self.Height + other.Height;
+        let adjusted = dist + self.Adjustment + other.Adjustment;
+        let seconds = if adjusted > 0.0 { adjusted } else { dist };
+        Some(Duration::from_secs_f64(seconds.max(0.0)))
+    }
+}
+
+/// rtt returns the estimated round trip time between two coordinates, or
+/// `None` when their dimensions differ. See `Coord::distance_to`.
+pub fn rtt(a: &Coord, b: &Coord) -> Option<Duration> {
+    a.distance_to(b)
+}
+
+/// CoordinateEntry represents a node and its associated network coordinate.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CoordinateEntry {
+    pub Node: Option<String>,
+    pub Segment: Option<String>,
+    pub Coord: Option<Coord>,
+}
+
+/// CoordinateDatacenterMap has the coordinates for servers in a given datacenter
+/// and area. Network coordinates are only compatible within the same area.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct CoordinateDatacenterMap {
+    pub Datacenter: Option<String>,
+    pub AreaID: Option<String>,
+    pub Coordinates: Option<Vec<CoordinateEntry>>,
+}
+
+impl ConsulConfig {
+    /// coordinate_datacenters is used to return the coordinates of all the servers in the WAN pool.
+    pub async fn coordinate_datacenters(
+        &self,
+    ) -> Result<Vec<CoordinateDatacenterMap>, ConsulError> {
+        self.get_json("/v1/coordinate/datacenters", None).await
+    }
+
+    /// coordinate_nodes is used to return the coordinates of all the nodes in the LAN pool.
+    pub async fn coordinate_nodes(
+        &self,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<CoordinateEntry>, ConsulError> {
+        self.get_json("/v1/coordinate/nodes", q).await
+    }
+
+    /// coordinate_node_rtt fetches the LAN coordinates and returns the
+    /// estimated round trip time between two nodes.
+    pub async fn coordinate_node_rtt(
+        &self,
+        node_a: &str,
+        node_b: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Duration, ConsulError> {
+        let entries = self.coordinate_nodes(q).await?;
+        let coord = |node: &str| {
+            entries
+                .iter()
+                .find(|entry| entry.Node.as_deref() == Some(node))
+                .and_then(|entry| entry.Coord.as_ref())
+                .ok_or_else(|| {
+                    ConsulError::InvalidArgument(format!(
+                        "Could not find a coordinate for node {:?}",
+                        node
+                    ))
+                })
+        };
+        let (a, b) = (coord(node_a)?, coord(node_b)?);
+        rtt(a, b).ok_or_else(|| {
+            ConsulError::InvalidArgument(format!(
+                "Coordinates of {:?} and {:?} have different dimensions",
+                node_a, node_b
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::coordinate::{rtt, Coord};
+    use crate::error::ConsulError;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use std::time::Duration;
+
+    #[test]
+    fn test_coordinate_nodes() {
+        let body = r#"[
+            {"Node": "node-a", "Segment": "", "Coord": {"Vec": [0.001, 0.0], "Error": 0.2, "Adjustment": 0.0, "Height": 0.0001}},
+            {"Node": "node-b", "Segment": "", "Coord": {"Vec": [0.004, 0.004], "Error": 0.3, "Adjustment": 0.0, "Height": 0.0001}}
+        ]"#;
+        let dcs = r#"[{"Datacenter": "dc1", "AreaID": "wan", "Coordinates": [
+            {"Node": "server-1.dc1", "Coord": {"Vec": [0.0, 0.0], "Error": 1.5, "Adjustment": 0.0, "Height": 0.00001}}
+        ]}]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body),
+            MockResponse::new(200, dcs),
+        ]);
+        let consul = mock_consul(&server);
+        let nodes = block_on(consul.coordinate_nodes(None)).unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].Node.as_deref(), Some("node-a"));
+        let a = nodes[0].Coord.as_ref().unwrap();
+        let b = nodes[1].Coord.as_ref().unwrap();
+        let rtt = a.distance_to(b).unwrap();
+        assert_eq!(rtt.as_micros(), 5200);
+        let dcs = block_on(consul.coordinate_datacenters()).unwrap();
+        assert_eq!(dcs[0].AreaID.as_deref(), Some("wan"));
+        assert_eq!(dcs[0].Coordinates.as_ref().unwrap().len(), 1);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/coordinate/nodes");
+        assert_eq!(requests[1].path, "/v1/coordinate/datacenters");
+    }
+
+    #[test]
+    fn test_distance_to() {
+        let a = Coord {
+            Vec: vec![0.0, 0.0],
+            Height: 0.001,
+            Adjustment: -0.0015,
+            ..Default::default()
+        };
+        let b = Coord {
+            Vec: vec![0.003, 0.004],
+            Height: 0.001,
+            ..Default::default()
+        };
+        assert_eq!(a.distance_to(&b), Some(Duration::from_micros(5500)));
+        assert_eq!(a.distance_to(&a), Some(Duration::from_micros(2000)));
+        let c = Coord {
+            Vec: vec![0.0; 8],
+            ..Default::default()
+        };
+        assert_eq!(a.distance_to(&c), None);
+    }
+
+    #[test]
+    fn test_rtt() {
+        // |(0.002, 0.006, 0.003)| = 0.007, plus heights 0.0005 and 0.0015
+        let a = Coord {
+            Vec: vec![0.001, -0.002, 0.0],
+            Height: 0.0005,
+            ..Default::default()
+        };
+        let b = Coord {
+            Vec: vec![0.003, 0.004, 0.003],
+            Height: 0.0015,
+            ..Default::default()
+        };
+        assert_eq!(rtt(&a, &b), Some(Duration::from_micros(9000)));
+        assert_eq!(rtt(&b, &a), rtt(&a, &b));
+        // a positive adjustment is added on top of the distance
+        let c = Coord {
+            Adjustment: 0.001,
+            ..b.clone()
+        };
+        assert_eq!(rtt(&a, &c), Some(Duration::from_micros(10000)));
+    }
+
+    #[test]
+    fn test_coordinate_node_rtt() {
+        let body = r#"[
+            {"Node": "node-a", "Coord": {"Vec": [0.0, 0.0], "Height": 0.0}},
+            {"Node": "node-b", "Coord": {"Vec": [0.006, 0.008], "Height": 0.0}}
+        ]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body),
+            MockResponse::new(200, body),
+        ]);
+        let consul = mock_consul(&server);
+        let rtt = block_on(consul.coordinate_node_rtt("node-a", "node-b", None)).unwrap();
+        assert_eq!(rtt, Duration::from_millis(10));
+        let err = block_on(consul.coordinate_node_rtt("node-a", "node-c", None)).unwrap_err();
+        assert!(matches!(err, ConsulError::InvalidArgument(_)));
+    }
+}
@@ -0,0 +1,191 @@
+use super::health::HealthError;
+use std::error::Error;
+use std::fmt;
+use surf::StatusCode;
+
+/// ConsulError is the error returned by the client methods.
+#[derive(Debug)]
+pub enum ConsulError {
+    /// NotConfigured means the client has no config or address to talk to.
+    NotConfigured,
+
+    /// InvalidArgument means the request was refused before being sent.
+    InvalidArgument(String),
+
+    /// NoAddress means no address is known for the watched service.
+    NoAddress,
+
+    /// Unauthorized means Consul rejected the ACL token with a 401 or 403.
+    Unauthorized { status: StatusCode, body: String },
+
+    /// Http is any other response with a non-2xx status.
+    Http { status: StatusCode, body: String },
+
+    /// Decode means the response body was not the expected JSON.
+    Decode(serde_json::Error),
+
+    /// Transport means the request could not be sent or its response read.
+    Transport(surf::Error),
+
+    /// Health means a health check in the response had a status that could
+    /// not be interpreted.
+    Health(HealthError),
+}
+
+impl ConsulError {
+    /// from_response maps a non-2xx response to Unauthorized or Http.
+    pub(crate) fn from_response(status: StatusCode, body: String) -> Self {
+        match status {
+            StatusCode::Unauthorized | StatusCode::Forbidden => {
+                ConsulError::Unauthorized { status, body }
+            }
+            _ => ConsulError::Http { status, body },
+        }
+    }
+
+    /// status returns the HTTP status Consul answered with, if any.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ConsulError::Unauthorized { status, .. } | ConsulError::Http { status, .. } => {
+                Some(*status)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ConsulError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsulError::NotConfigured => write!(f, "client init err"),
+            ConsulError::InvalidArgument(msg) => write!(f, "{}", msg),
+            ConsulError::NoAddress => write!(f, "consul server address is empty"),
+            ConsulError::Unauthorized { status, body } | ConsulError::Http { status, body } => {
+                if body.is_empty() {
+                    write!(f, "Unexpected response code: {}", *status as u16)
+                } else {
+                    write!(f, "Unexpected response code: {} ({})", *status as u16, body)
+                }
+            }
+            ConsulError::Decode(err) => write!(f, "Failed to decode response: {}", err),
+            ConsulError::Transport(err) => write!(f, "{}", err),
+            ConsulError::Health(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ConsulError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConsulError::Decode(err) => Some(err),
+            ConsulError::Transport(err) => Some(err.as_ref()),
+            ConsulError::Health(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<surf::Error> for ConsulError {
+    fn from(err: surf::Error) -> Self {
+        ConsulError::Transport(err)
+    }
+}
+
+impl From<serde_json::Error> for ConsulError {
+    fn from(err: serde_json::Error) -> Self {
+        ConsulError::Decode(err)
+    }
+}
+
+impl From<HealthError> for ConsulError {
+    fn from(err: HealthError) -> Self {
+        ConsulError::Health(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::api::{Config, ConsulConfig};
+    use crate::error::ConsulError;
+    use crate::kv::KVPair;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use surf::StatusCode;
+
+    #[test]
+    fn test_error_variants() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(403, "Permission denied"),
+            MockResponse::new(500, "rpc error"),
+            MockResponse::new(200, "not json"),
+        ]);
+        let consul = mock_consul(&server);
+        let err = block_on(consul.agent_self(None)).unwrap_err();
+        assert!(matches!(err, ConsulError::Unauthorized { .. }));
+        assert_eq!(err.status(), Some(StatusCode::Forbidden));
+        let err = block_on(consul.reload(None)).unwrap_err();
+        assert!(matches!(
+            err,
+            ConsulError::Http {
+                status: StatusCode::InternalServerError,
+                ..
+            }
+        ));
+        let err = block_on(consul.catalog_datacenters()).unwrap_err();
+        assert!(matches!(err, ConsulError::Decode(_)));
+        assert_eq!(err.status(), None);
+
+        let err = block_on(consul.kv_put(&KVPair::default(), None)).unwrap_err();
+        assert!(matches!(err, ConsulError::InvalidArgument(_)));
+    }
+
+    #[test]
+    fn test_error_body() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(403, "Permission denied\n"),
+            MockResponse::new(500, ""),
+        ]);
+        let consul = mock_consul(&server);
+        let err = block_on(consul.kv_get("config/web/port", None)).unwrap_err();
+        match &err {
+            ConsulError::Unauthorized { status, body } => {
+                assert_eq!(*status, StatusCode::Forbidden);
+                assert_eq!(body, "Permission denied");
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(
+            err.to_string(),
+            "Unexpected response code: 403 (Permission denied)"
+        );
+        let err = block_on(consul.reload(None)).unwrap_err();
+        assert_eq!(err.to_string(), "Unexpected response code: 500");
+    }
+
+    #[test]
+    fn test_error_not_configured() {
+        let consul = ConsulConfig {
+            config: None,
+            watch_services: None,
+        };
+        let err = block_on(consul.catalog_datacenters()).unwrap_err();
+        assert!(matches!(err, ConsulError::NotConfigured));
+        let consul = ConsulConfig {
+            config: Some(Config::default()),
+            watch_services: None,
+        };
+        let err = block_on(consul.agent_self(None)).unwrap_err();
+        assert!(matches!(err, ConsulError::NotConfigured));
+    }
+
+    #[test]
+    fn test_error_transport() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let consul = ConsulConfig::builder().address(&address).build();
+        let err = block_on(consul.reload(None)).unwrap_err();
+        assert!(matches!(err, ConsulError::Transport(_)));
+    }
+}
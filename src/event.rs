@@ -0,0 +1,104 @@
+use super::api::{append_query, decode_json, require_ok, ConsulConfig, QueryOptions, WriteOptions};
+use super::error::ConsulError;
+use serde_derive::{Deserialize, Serialize};
+use surf::http::Method;
+
+/// UserEvent represents an event that was fired by the user
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct UserEvent {
+    pub ID: Option<String>,
+    pub Name: Option<String>,
+    #[serde(default, with = "crate::kv::base64_value")]
+    pub Payload: Option<Vec<u8>>,
+    pub NodeFilter: Option<String>,
+    pub ServiceFilter: Option<String>,
+    pub TagFilter: Option<String>,
+    pub Version: Option<u32>,
+    pub LTime: Option<u64>,
+}
+
+impl ConsulConfig {
+    /// event_fire is used to fire a new user event. Only the Name and
+    /// Payload are given here; the returned event carries its new ID.
+    pub async fn event_fire(
+        &self,
+        name: &str,
+        payload: &[u8],
+        q: Option<&WriteOptions>,
+    ) -> Result<UserEvent, ConsulError> {
+        let path = format!("/v1/event/fire/{}", name);
+        let mut req = self.new_request(Method::Put, &path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        req.set_body(payload.to_vec());
+        let mut res = require_ok(self.send(req).await?).await?;
+        let out: UserEvent = decode_json(&mut res).await?;
+        Ok(out)
+    }
+
+    /// event_list is used to get the most recent events an agent has received.
+    /// This list can be optionally filtered by the name.
+    pub async fn event_list(
+        &self,
+        name: Option<&str>,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<UserEvent>, ConsulError> {
+        let mut req = self.new_request(Method::Get, "/v1/event/list").await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        if let Some(name) = name.filter(|name| !name.is_empty()) {
+            append_query(&mut req, "name", name);
+        }
+        let mut res = require_ok(self.send(req).await?).await?;
+        let out: Vec<UserEvent> = decode_json(&mut res).await?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+
+    #[test]
+    fn test_event_fire_list() {
+        let fired = r#"{
+            "ID": "b54fe110-7af5-cafc-d1fb-afc8ba432b1c",
+            "Name": "deploy",
+            "Payload": null,
+            "NodeFilter": "",
+            "ServiceFilter": "",
+            "TagFilter": "",
+            "Version": 1,
+            "LTime": 0
+        }"#;
+        let listed = r#"[{
+            "ID": "b54fe110-7af5-cafc-d1fb-afc8ba432b1c",
+            "Name": "deploy",
+            "Payload": "djEuMi4z",
+            "Version": 1,
+            "LTime": 19
+        }]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, fired),
+            MockResponse::new(200, listed),
+        ]);
+        let consul = mock_consul(&server);
+        let event = block_on(consul.event_fire("deploy", b"v1.2.3", None)).unwrap();
+        let events = block_on(consul.event_list(Some("deploy"), None)).unwrap();
+        assert_eq!(events[0].ID, event.ID);
+        assert_eq!(events[0].Name.as_deref(), Some("deploy"));
+        assert_eq!(events[0].Payload.as_deref(), Some(&b"v1.2.3"[..]));
+        assert_eq!(events[0].LTime, Some(19));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/v1/event/fire/deploy");
+        assert_eq!(requests[0].body, b"v1.2.3");
+        assert_eq!(requests[1].path, "/v1/event/list?name=deploy");
+    }
+}
@@ -1,35 +1,168 @@
 #[allow(dead_code)]
 use super::agent;
+use super::api::join_host_port;
 use super::catalog;
-use async_std::sync::Arc;
+use super::error::ConsulError;
+use super::serde::format_duration;
 use lazy_static::lazy_static;
+use serde::ser::{SerializeStruct, Serializer};
 use serde_derive::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::{HashMap, LinkedList};
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
+use surf::http::Method;
 
-lazy_static!(
-    /// HealthAny is special, and is used as a wild card, not as a specific state.
-    #[derive(Debug)]
-    pub static ref HEALTH_ANY: Arc<String> = {
-        Arc::new(String::from("any"))
-    };
-    #[derive(Debug)]
-    pub static ref HEALTH_PASSING:Arc<String> = {
-        Arc::new(String::from("passing"))
-    };
-    #[derive(Debug)]
-    pub static ref HEALTH_WARNING:Arc<String>  = {
-        Arc::new(String::from("warning"))
-    };
-    #[derive(Debug)]
-    pub static ref HEALTH_CRITICAL:Arc<String> = {
-        Arc::new(String::from("critical"))
-    };
-    #[derive(Debug)]
-    pub static ref HEALTH_MAINT:Arc<String> = {
-        Arc::new(String::from("maintenance"))
-    };
-);
+pub use self::deprecated_status::*;
+
+/// NODE_MAINT is the special key set by a node in maintenance mode.
+pub const NODE_MAINT: &str = "_node_maintenance";
+
+/// SERVICE_MAINT_PREFIX is the prefix for a service in maintenance mode.
+pub const SERVICE_MAINT_PREFIX: &str = "_service_maintenance:";
+
+/// service_maint_check_id returns the ID of the check Consul registers while
+/// the given service is in maintenance mode.
+pub fn service_maint_check_id(service_id: &str) -> String {
+    format!("{}{}", SERVICE_MAINT_PREFIX, service_id)
+}
+
+/// is_maintenance_check_id reports whether a check ID is one of the checks
+/// Consul registers for node or service maintenance mode.
+pub fn is_maintenance_check_id(check_id: &str) -> bool {
+    check_id == NODE_MAINT || check_id.starts_with(SERVICE_MAINT_PREFIX)
+}
+
+// The string constants predate `HealthStatus` and are kept for one release so
+// existing callers keep compiling.
+#[allow(deprecated)]
+mod deprecated_status {
+    use super::HealthStatus;
+    use lazy_static::lazy_static;
+    use std::sync::Arc;
+
+    lazy_static!(
+        /// HealthAny is special, and is used as a wild card, not as a specific state.
+        #[derive(Debug)]
+        #[deprecated(note = "use `HealthStatus::Any` instead")]
+        pub static ref HEALTH_ANY: Arc<String> = {
+            Arc::new(HealthStatus::Any.to_string())
+        };
+        #[derive(Debug)]
+        #[deprecated(note = "use `HealthStatus::Passing` instead")]
+        pub static ref HEALTH_PASSING:Arc<String> = {
+            Arc::new(HealthStatus::Passing.to_string())
+        };
+        #[derive(Debug)]
+        #[deprecated(note = "use `HealthStatus::Warning` instead")]
+        pub static ref HEALTH_WARNING:Arc<String>  = {
+            Arc::new(HealthStatus::Warning.to_string())
+        };
+        #[derive(Debug)]
+        #[deprecated(note = "use `HealthStatus::Critical` instead")]
+        pub static ref HEALTH_CRITICAL:Arc<String> = {
+            Arc::new(HealthStatus::Critical.to_string())
+        };
+        #[derive(Debug)]
+        #[deprecated(note = "use `HealthStatus::Maintenance` instead")]
+        pub static ref HEALTH_MAINT:Arc<String> = {
+            Arc::new(HealthStatus::Maintenance.to_string())
+        };
+    );
+}
+
+/// HealthStatus is the state of a health check as reported by Consul.
+///
+/// The ordering follows the precedence used when aggregating several checks:
+/// `Maintenance > Critical > Warning > Passing`. `Any` is only meaningful as a
+/// wild card in queries and sorts below every concrete state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HealthStatus {
+    Any,
+    Passing,
+    Warning,
+    Critical,
+    Maintenance,
+}
+
+impl HealthStatus {
+    /// as_str returns the Consul wire representation of the status.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Any => "any",
+            HealthStatus::Passing => "passing",
+            HealthStatus::Warning => "warning",
+            HealthStatus::Critical => "critical",
+            HealthStatus::Maintenance => "maintenance",
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            HealthStatus::Any => 0,
+            HealthStatus::Passing => 1,
+            HealthStatus::Warning => 2,
+            HealthStatus::Critical => 3,
+            HealthStatus::Maintenance => 4,
+        }
+    }
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HealthStatus {
+    type Err = HealthError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(HealthStatus::Any),
+            "passing" => Ok(HealthStatus::Passing),
+            "warning" => Ok(HealthStatus::Warning),
+            "critical" => Ok(HealthStatus::Critical),
+            "maintenance" => Ok(HealthStatus::Maintenance),
+            _ => Err(HealthError::UnknownStatus(s.to_string())),
+        }
+    }
+}
+
+impl PartialOrd for HealthStatus {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HealthStatus {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.precedence().cmp(&other.precedence())
+    }
+}
+
+/// HealthError is returned when a health status cannot be interpreted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthError {
+    /// UnknownStatus is a status string Consul is not expected to return.
+    UnknownStatus(String),
+    /// MissingStatus is a check, identified by its CheckID, without a status.
+    MissingStatus(String),
+}
+
+impl fmt::Display for HealthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthError::UnknownStatus(status) => write!(f, "unknown health status: {:?}", status),
+            HealthError::MissingStatus(check_id) => {
+                write!(f, "health check {:?} has no status", check_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HealthError {}
 
 lazy_static!(
     #[derive(Debug)]
@@ -67,10 +200,77 @@ pub struct HealthCheck {
     pub ModifyIndex: Option<usize>,
 }
 
+impl HealthCheck {
+    /// status returns the parsed status of the check. Maintenance checks are
+    /// reported as `Maintenance` whatever their status string says.
+    pub fn status(&self) -> Result<HealthStatus, HealthError> {
+        let check_id = self.CheckID.as_deref().unwrap_or_default();
+        if is_maintenance_check_id(check_id) {
+            return Ok(HealthStatus::Maintenance);
+        }
+        let status = self
+            .Status
+            .as_deref()
+            .ok_or_else(|| HealthError::MissingStatus(check_id.to_string()))?;
+        HealthStatus::from_str(status)
+    }
+
+    /// is_passing reports whether the check is passing.
+    pub fn is_passing(&self) -> bool {
+        self.status() == Ok(HealthStatus::Passing)
+    }
+
+    /// check_kind returns the kind of the check, from its `Type`.
+    pub fn check_kind(&self) -> CheckKind {
+        match self.Type.as_deref().unwrap_or_default() {
+            "http" => CheckKind::Http,
+            "tcp" => CheckKind::Tcp,
+            "ttl" => CheckKind::Ttl,
+            "script" => CheckKind::Script,
+            "grpc" => CheckKind::Grpc,
+            "alias" => CheckKind::Alias,
+            "docker" => CheckKind::Docker,
+            other => CheckKind::Other(other.to_string()),
+        }
+    }
+
+    /// http_status_code returns the status code an HTTP check last got, as
+    /// found in its output, e.g. `HTTP GET http://10.0.0.1/health: 200 OK`.
+    /// It is `None` for other checks or when the output holds no status,
+    /// such as after a connection failure.
+    pub fn http_status_code(&self) -> Option<u16> {
+        if self.check_kind() != CheckKind::Http {
+            return None;
+        }
+        let output = self.Output.as_deref()?.strip_prefix("HTTP ")?;
+        let (_, rest) = output.split_once(": ")?;
+        let code = rest.split_whitespace().next()?;
+        if code.len() != 3 {
+            return None;
+        }
+        code.parse().ok().filter(|code| (100..600).contains(code))
+    }
+}
+
+/// CheckKind is the kind of a health check, as reported in its `Type`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CheckKind {
+    Http,
+    Tcp,
+    Ttl,
+    Script,
+    Grpc,
+    Alias,
+    Docker,
+    /// Other holds any other type, including the empty type of checks such
+    /// as `serfHealth` and the maintenance checks.
+    Other(String),
+}
+
 type ReadableDuration = Duration;
 
 /// HealthCheckDefinition is used to store the details about a health check's execution.
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Deserialize)]
 #[allow(non_snake_case)]
 pub struct HealthCheckDefinition {
     pub HTTP: Option<String>,
@@ -80,21 +280,208 @@ pub struct HealthCheckDefinition {
     pub TLSServerName: Option<String>,
     pub TLSSkipVerify: Option<bool>,
     pub TCP: Option<String>,
+    pub GRPC: Option<String>,
+    pub GRPCUseTLS: Option<bool>,
+    pub AliasNode: Option<String>,
+    pub AliasService: Option<String>,
+    #[serde(default, deserialize_with = "crate::serde::deserialize_duration")]
     pub IntervalDuration: Option<Duration>,
+    #[serde(default, deserialize_with = "crate::serde::deserialize_duration")]
     pub TimeoutDuration: Option<Duration>,
+    #[serde(default, deserialize_with = "crate::serde::deserialize_duration")]
     pub DeregisterCriticalServiceAfterDuration: Option<Duration>,
 
     /// DEPRECATED in Consul 1.4.1. Use the above time.Duration fields instead.
+    #[serde(default, deserialize_with = "crate::serde::deserialize_duration")]
     pub Interval: Option<ReadableDuration>,
+    #[serde(default, deserialize_with = "crate::serde::deserialize_duration")]
     pub Timeout: Option<ReadableDuration>,
+    #[serde(default, deserialize_with = "crate::serde::deserialize_duration")]
     pub DeregisterCriticalServiceAfter: Option<ReadableDuration>,
 }
 
+// Consul reads the durations of a definition from the deprecated field names,
+// so the `*Duration` fields are sent under those, as the Go client does. A
+// `*Duration` field takes precedence over its deprecated counterpart.
+impl serde::Serialize for HealthCheckDefinition {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let durations = [
+            ("Interval", self.IntervalDuration.or(self.Interval)),
+            ("Timeout", self.TimeoutDuration.or(self.Timeout)),
+            (
+                "DeregisterCriticalServiceAfter",
+                self.DeregisterCriticalServiceAfterDuration
+                    .or(self.DeregisterCriticalServiceAfter),
+            ),
+        ];
+        let mut state = s.serialize_struct("HealthCheckDefinition", 14)?;
+        state.serialize_field("HTTP", &self.HTTP)?;
+        state.serialize_field("Header", &self.Header)?;
+        state.serialize_field("Method", &self.Method)?;
+        state.serialize_field("Body", &self.Body)?;
+        state.serialize_field("TLSServerName", &self.TLSServerName)?;
+        state.serialize_field("TLSSkipVerify", &self.TLSSkipVerify)?;
+        state.serialize_field("TCP", &self.TCP)?;
+        state.serialize_field("GRPC", &self.GRPC)?;
+        state.serialize_field("GRPCUseTLS", &self.GRPCUseTLS)?;
+        state.serialize_field("AliasNode", &self.AliasNode)?;
+        state.serialize_field("AliasService", &self.AliasService)?;
+        for (key, duration) in durations.iter() {
+            match duration {
+                Some(duration) => state.serialize_field(key, &format_duration(*duration))?,
+                None => state.skip_field(key)?,
+            }
+        }
+        state.end()
+    }
+}
+
+impl HealthCheckDefinition {
+    pub fn builder() -> HealthCheckDefinitionBuilder {
+        HealthCheckDefinitionBuilder::default()
+    }
+}
+
+/// HealthCheckDefinitionBuilder is used to build a `HealthCheckDefinition`.
+/// Durations are only set on the `*Duration` fields, never on the deprecated
+/// ones.
+#[derive(Debug, Clone, Default)]
+pub struct HealthCheckDefinitionBuilder {
+    definition: HealthCheckDefinition,
+}
+
+impl HealthCheckDefinitionBuilder {
+    pub fn http(mut self, url: &str) -> Self {
+        self.definition.HTTP = Some(String::from(url));
+        self
+    }
+
+    pub fn tcp(mut self, address: &str) -> Self {
+        self.definition.TCP = Some(String::from(address));
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.definition.IntervalDuration = Some(interval);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.definition.TimeoutDuration = Some(timeout);
+        self
+    }
+
+    pub fn deregister_after(mut self, after: Duration) -> Self {
+        self.definition.DeregisterCriticalServiceAfterDuration = Some(after);
+        self
+    }
+
+    /// header adds a value for an HTTP header. Calling it again with the same
+    /// name adds another value rather than replacing the first.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.definition
+            .Header
+            .get_or_insert_with(HashMap::new)
+            .entry(String::from(name))
+            .or_default()
+            .push(String::from(value));
+        self
+    }
+
+    pub fn method(mut self, method: Method) -> Self {
+        self.definition.Method = Some(method.to_string());
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.definition.Body = Some(String::from(body));
+        self
+    }
+
+    pub fn tls_server_name(mut self, name: &str) -> Self {
+        self.definition.TLSServerName = Some(String::from(name));
+        self
+    }
+
+    pub fn tls_skip_verify(mut self, skip: bool) -> Self {
+        self.definition.TLSSkipVerify = Some(skip);
+        self
+    }
+
+    /// build returns the definition. Exactly one of HTTP and TCP must be set,
+    /// and the HTTP only settings are rejected on a TCP check.
+    pub fn build(self) -> Result<HealthCheckDefinition, ConsulError> {
+        let definition = self.definition;
+        match (&definition.HTTP, &definition.TCP) {
+            (None, None) => {
+                return Err(ConsulError::InvalidArgument(String::from(
+                    "health check needs an HTTP or TCP target",
+                )))
+            }
+            (Some(_), Some(_)) => {
+                return Err(ConsulError::InvalidArgument(String::from(
+                    "health check cannot have both an HTTP and a TCP target",
+                )))
+            }
+            (None, Some(_))
+                if definition.Header.is_some()
+                    || definition.Method.is_some()
+                    || definition.Body.is_some() =>
+            {
+                return Err(ConsulError::InvalidArgument(String::from(
+                    "header, method and body only apply to HTTP checks",
+                )))
+            }
+            _ => {}
+        }
+        Ok(definition)
+    }
+}
+
 /// HealthChecks is a collection of HealthCheck structs.
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[allow(non_snake_case)]
 pub struct HealthChecks(Vec<HealthCheck>);
 
+impl HealthChecks {
+    /// aggregates_status returns the "best" status for the list of health checks.
+    /// Because a given entry may have many service and node-level health checks
+    /// attached, this function determines the best representative of the status
+    /// as single string using the following heuristic:
+    ///
+    ///  maintenance > critical > warning > passing
+    ///
+    /// An empty list is considered passing. A check without a status or with a
+    /// status Consul does not define is reported as an error.
+    pub fn aggregates_status(&self) -> Result<HealthStatus, HealthError> {
+        self.0
+            .iter()
+            .try_fold(HealthStatus::Passing, |status, check| {
+                match check.status()? {
+                    HealthStatus::Any => {
+                        Err(HealthError::UnknownStatus(HealthStatus::Any.to_string()))
+                    }
+                    check_status => Ok(status.max(check_status)),
+                }
+            })
+    }
+
+    /// worst returns the most severe status among the checks, following the
+    /// ordering of `HealthStatus`. Unlike aggregates_status it never fails: a
+    /// check with a missing or unknown status counts as critical. An empty
+    /// list is passing.
+    pub fn worst(&self) -> HealthStatus {
+        self.0
+            .iter()
+            .map(|check| match check.status() {
+                Ok(HealthStatus::Any) | Err(_) => HealthStatus::Critical,
+                Ok(status) => status,
+            })
+            .max()
+            .unwrap_or(HealthStatus::Passing)
+    }
+}
+
 /// ServiceEntry is used for the health service endpoint
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 #[allow(non_snake_case)]
@@ -104,9 +491,399 @@ pub struct ServiceEntry {
     pub Checks: Option<HealthChecks>,
 }
 
+impl ServiceEntry {
+    /// effective_address returns the `host:port` the instance is reached at,
+    /// resolved the way Consul DNS does: the service address when set and
+    /// the node address otherwise. It is `None` without an address or port.
+    pub fn effective_address(&self) -> Option<String> {
+        let service = self.Service.as_ref()?;
+        let address = service
+            .Address
+            .as_deref()
+            .filter(|address| !address.is_empty())
+            .or_else(|| self.Node.as_ref().and_then(|node| node.Address.as_deref()))
+            .filter(|address| !address.is_empty())?;
+        Some(join_host_port(address, service.Port?))
+    }
+
+    /// aggregated_status returns the worst status among the entry's checks,
+    /// see `HealthChecks::worst`. An entry without checks is passing.
+    pub fn aggregated_status(&self) -> HealthStatus {
+        self.Checks
+            .as_ref()
+            .map_or(HealthStatus::Passing, HealthChecks::worst)
+    }
+}
+
 #[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct ServiceAddress {
     pub index: u64,
     pub address: Vec<String>,
     pub address_link: LinkedList<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_maintenance_check_id, service_maint_check_id, CheckKind, HealthCheck,
+        HealthCheckDefinition, HealthChecks, HealthError, HealthStatus, ServiceEntry, NODE_MAINT,
+        SERVICE_MAINT_PREFIX,
+    };
+    use crate::error::ConsulError;
+    use crate::serde::format_duration;
+    use std::str::FromStr;
+    use std::time::Duration;
+    use surf::http::Method;
+
+    fn check(check_id: &str, status: Option<&str>) -> HealthCheck {
+        HealthCheck {
+            CheckID: Some(String::from(check_id)),
+            Status: status.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_health_status_wire_strings() {
+        let statuses = [
+            HealthStatus::Any,
+            HealthStatus::Passing,
+            HealthStatus::Warning,
+            HealthStatus::Critical,
+            HealthStatus::Maintenance,
+        ];
+        for status in statuses.iter() {
+            let wire = status.to_string();
+            assert_eq!(HealthStatus::from_str(&wire).unwrap(), *status);
+        }
+        assert_eq!(HealthStatus::Maintenance.to_string(), "maintenance");
+        assert!(HealthStatus::from_str("unknown").is_err());
+    }
+
+    #[test]
+    fn test_health_status_precedence() {
+        assert!(HealthStatus::Maintenance > HealthStatus::Critical);
+        assert!(HealthStatus::Critical > HealthStatus::Warning);
+        assert!(HealthStatus::Warning > HealthStatus::Passing);
+        assert!(HealthStatus::Passing > HealthStatus::Any);
+    }
+
+    #[test]
+    fn test_aggregates_status_empty() {
+        let checks = HealthChecks::default();
+        assert_eq!(checks.aggregates_status(), Ok(HealthStatus::Passing));
+    }
+
+    #[test]
+    fn test_aggregates_status_unknown() {
+        let checks = HealthChecks(vec![
+            check("serfHealth", Some("passing")),
+            check("web", Some("exploded")),
+        ]);
+        assert_eq!(
+            checks.aggregates_status(),
+            Err(HealthError::UnknownStatus(String::from("exploded")))
+        );
+
+        let checks = HealthChecks(vec![check("web", None)]);
+        assert_eq!(
+            checks.aggregates_status(),
+            Err(HealthError::MissingStatus(String::from("web")))
+        );
+    }
+
+    #[test]
+    fn test_aggregates_status_mixed() {
+        let checks = HealthChecks(vec![
+            check("serfHealth", Some("passing")),
+            check("web", Some("warning")),
+        ]);
+        assert_eq!(checks.aggregates_status(), Ok(HealthStatus::Warning));
+
+        let checks = HealthChecks(vec![
+            check("web", Some("critical")),
+            check("serfHealth", Some("passing")),
+            check("db", Some("warning")),
+        ]);
+        assert_eq!(checks.aggregates_status(), Ok(HealthStatus::Critical));
+
+        let maint = format!("{}web", SERVICE_MAINT_PREFIX);
+        let checks = HealthChecks(vec![
+            check("web", Some("critical")),
+            check(&maint, Some("critical")),
+        ]);
+        assert_eq!(checks.aggregates_status(), Ok(HealthStatus::Maintenance));
+    }
+
+    #[test]
+    fn test_aggregates_status_maintenance_status() {
+        let checks = HealthChecks(vec![
+            check("serfHealth", Some("passing")),
+            check("web", Some("maintenance")),
+        ]);
+        assert_eq!(checks.aggregates_status(), Ok(HealthStatus::Maintenance));
+        assert_eq!(checks.worst(), HealthStatus::Maintenance);
+
+        let checks = HealthChecks(vec![check("web", Some("any"))]);
+        assert_eq!(
+            checks.aggregates_status(),
+            Err(HealthError::UnknownStatus(String::from("any")))
+        );
+    }
+
+    #[test]
+    fn test_health_check_definition_builder_http() {
+        let definition = HealthCheckDefinition::builder()
+            .http("http://127.0.0.1:8080/health")
+            .method(Method::Post)
+            .header("Accept", "application/json")
+            .header("Accept", "text/plain")
+            .interval(Duration::from_secs(10))
+            .timeout(Duration::from_secs(1))
+            .deregister_after(Duration::from_secs(90))
+            .build()
+            .unwrap();
+        assert_eq!(definition.Method.as_deref(), Some("POST"));
+        assert_eq!(
+            definition.Header.as_ref().unwrap()["Accept"],
+            vec!["application/json", "text/plain"]
+        );
+        assert_eq!(definition.IntervalDuration, Some(Duration::from_secs(10)));
+        assert_eq!(definition.Interval, None);
+        assert_eq!(definition.DeregisterCriticalServiceAfter, None);
+        let json = serde_json::to_value(&definition).unwrap();
+        // Consul reads the fields without the Duration suffix.
+        assert_eq!(json["Interval"], format_duration(Duration::from_secs(10)));
+        assert_eq!(json["Interval"], "10s");
+        assert_eq!(json["Timeout"], "1s");
+        assert_eq!(json["DeregisterCriticalServiceAfter"], "1m30s");
+        assert!(json.get("IntervalDuration").is_none());
+        assert!(json.get("DeregisterCriticalServiceAfterDuration").is_none());
+    }
+
+    #[test]
+    fn test_health_check_definition_builder_tcp() {
+        let definition = HealthCheckDefinition::builder()
+            .tcp("127.0.0.1:5432")
+            .interval(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        assert_eq!(definition.TCP.as_deref(), Some("127.0.0.1:5432"));
+        assert_eq!(definition.HTTP, None);
+
+        let invalid = [
+            HealthCheckDefinition::builder().interval(Duration::from_secs(5)),
+            HealthCheckDefinition::builder()
+                .http("http://127.0.0.1:8080/health")
+                .tcp("127.0.0.1:8080"),
+            HealthCheckDefinition::builder()
+                .tcp("127.0.0.1:5432")
+                .method(Method::Get),
+        ];
+        for builder in invalid.iter() {
+            let err = builder.clone().build().unwrap_err();
+            assert!(matches!(err, ConsulError::InvalidArgument(_)));
+        }
+    }
+
+    #[test]
+    fn test_health_check_definition_grpc_round_trip() {
+        let body = r#"{
+            "GRPC": "127.0.0.1:9090/health.v1.Health",
+            "GRPCUseTLS": true,
+            "TLSServerName": "web.service.consul",
+            "Interval": "10s",
+            "Timeout": "1s"
+        }"#;
+        let definition: HealthCheckDefinition = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            definition.GRPC.as_deref(),
+            Some("127.0.0.1:9090/health.v1.Health")
+        );
+        assert_eq!(definition.GRPCUseTLS, Some(true));
+        assert_eq!(definition.Interval, Some(Duration::from_secs(10)));
+
+        let json = serde_json::to_value(&definition).unwrap();
+        let original: serde_json::Value = serde_json::from_str(body).unwrap();
+        for (key, value) in original.as_object().unwrap() {
+            assert_eq!(&json[key], value, "{} did not round-trip", key);
+        }
+
+        // Unset durations are left out rather than sent as null.
+        assert!(json.get("DeregisterCriticalServiceAfter").is_none());
+        assert!(json.get("IntervalDuration").is_none());
+
+        let alias: HealthCheckDefinition =
+            serde_json::from_str(r#"{"AliasNode": "node-1", "AliasService": "web-1"}"#).unwrap();
+        let json = serde_json::to_value(&alias).unwrap();
+        assert_eq!(json["AliasNode"], "node-1");
+        assert_eq!(json["AliasService"], "web-1");
+    }
+
+    #[test]
+    fn test_aggregates_status_many_checks() {
+        let mut checks: Vec<HealthCheck> = (0..1000)
+            .map(|i| check(&format!("service:web-{}", i), Some("passing")))
+            .collect();
+        assert_eq!(
+            HealthChecks(checks.clone()).aggregates_status(),
+            Ok(HealthStatus::Passing)
+        );
+        checks[500].Status = Some(String::from("warning"));
+        assert_eq!(
+            HealthChecks(checks.clone()).aggregates_status(),
+            Ok(HealthStatus::Warning)
+        );
+        checks[999].Status = Some(String::from("critical"));
+        assert_eq!(
+            HealthChecks(checks.clone()).aggregates_status(),
+            Ok(HealthStatus::Critical)
+        );
+        checks.push(check(&format!("{}web-3", SERVICE_MAINT_PREFIX), None));
+        assert_eq!(
+            HealthChecks(checks).aggregates_status(),
+            Ok(HealthStatus::Maintenance)
+        );
+    }
+
+    #[test]
+    fn test_worst() {
+        assert_eq!(HealthChecks::default().worst(), HealthStatus::Passing);
+        let checks = HealthChecks(vec![
+            check("serfHealth", Some("passing")),
+            check("service:web-1", Some("passing")),
+        ]);
+        assert_eq!(checks.worst(), HealthStatus::Passing);
+        let checks = HealthChecks(vec![
+            check("serfHealth", Some("passing")),
+            check("service:web-1", Some("warning")),
+            check("service:web-2", Some("passing")),
+        ]);
+        assert_eq!(checks.worst(), HealthStatus::Warning);
+        let checks = HealthChecks(vec![
+            check("service:web-1", Some("warning")),
+            check("service:web-2", Some("bogus")),
+        ]);
+        assert_eq!(checks.worst(), HealthStatus::Critical);
+        assert!(checks.aggregates_status().is_err());
+        let checks = HealthChecks(vec![
+            check("service:web-1", Some("critical")),
+            check(&format!("{}web-1", SERVICE_MAINT_PREFIX), Some("critical")),
+        ]);
+        assert_eq!(checks.worst(), HealthStatus::Maintenance);
+        assert_eq!(format!("{}", checks.worst()), "maintenance");
+        assert_eq!(format!("{}", HealthStatus::Warning), "warning");
+    }
+
+    #[test]
+    fn test_is_passing() {
+        assert!(check("serfHealth", Some("passing")).is_passing());
+        assert!(!check("serfHealth", Some("warning")).is_passing());
+        assert!(!check("serfHealth", None).is_passing());
+        assert!(!check(NODE_MAINT, Some("passing")).is_passing());
+    }
+
+    #[test]
+    fn test_maintenance_check_id() {
+        assert_eq!(
+            service_maint_check_id("web-1"),
+            "_service_maintenance:web-1"
+        );
+        assert!(is_maintenance_check_id(NODE_MAINT));
+        assert!(is_maintenance_check_id(&service_maint_check_id("web-1")));
+        assert!(!is_maintenance_check_id("serfHealth"));
+        assert!(!is_maintenance_check_id("service:web-1"));
+        assert!(!is_maintenance_check_id("_node_maintenance_extra"));
+        assert!(!is_maintenance_check_id(""));
+    }
+
+    #[test]
+    fn test_check_kind_and_http_status_code() {
+        let check = |kind: &str, output: &str| HealthCheck {
+            Type: Some(String::from(kind)),
+            Output: Some(String::from(output)),
+            ..Default::default()
+        };
+        let cases = [
+            (
+                check("http", "HTTP GET http://10.0.0.1:8080/health: 200 OK Output: ok"),
+                CheckKind::Http,
+                Some(200),
+            ),
+            (
+                check(
+                    "http",
+                    "HTTP POST https://[::1]:8443/health: 503 Service Unavailable Output: ",
+                ),
+                CheckKind::Http,
+                Some(503),
+            ),
+            (
+                check(
+                    "http",
+                    "Get \"http://10.0.0.1:8080/health\": dial tcp 10.0.0.1:8080: connect: connection refused",
+                ),
+                CheckKind::Http,
+                None,
+            ),
+            (
+                check("tcp", "TCP connect 10.0.0.1:5432: Success"),
+                CheckKind::Tcp,
+                None,
+            ),
+            (check("ttl", "HTTP GET x: 200 OK"), CheckKind::Ttl, None),
+            (check("grpc", ""), CheckKind::Grpc, None),
+            (check("alias", ""), CheckKind::Alias, None),
+            (check("script", ""), CheckKind::Script, None),
+            (check("", "Agent alive and reachable"), CheckKind::Other(String::new()), None),
+            (
+                check("h2ping", ""),
+                CheckKind::Other(String::from("h2ping")),
+                None,
+            ),
+        ];
+        for (check, kind, code) in cases.iter() {
+            assert_eq!(&check.check_kind(), kind, "{:?}", check.Output);
+            assert_eq!(&check.http_status_code(), code, "{:?}", check.Output);
+        }
+    }
+
+    #[test]
+    fn test_service_entry_effective_address() {
+        let entry: ServiceEntry = serde_json::from_str(
+            r#"{"Node": {"Node": "node-a", "Address": "10.0.0.1"},
+                "Service": {"ID": "web-1", "Service": "web", "Address": "172.17.0.3", "Port": 8080}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            entry.effective_address().as_deref(),
+            Some("172.17.0.3:8080")
+        );
+
+        let entry: ServiceEntry = serde_json::from_str(
+            r#"{"Node": {"Node": "node-b", "Address": "fe80::1"},
+                "Service": {"ID": "web-2", "Service": "web", "Address": "", "Port": 8080}}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.effective_address().as_deref(), Some("[fe80::1]:8080"));
+
+        let entry: ServiceEntry = serde_json::from_str(
+            r#"{"Node": {"Node": "node-c", "Address": "10.0.0.3"},
+                "Service": {"ID": "web-3", "Service": "web"}}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.effective_address(), None);
+        assert_eq!(ServiceEntry::default().effective_address(), None);
+    }
+
+    #[test]
+    fn test_service_entry_aggregated_status() {
+        let mut entry = ServiceEntry::default();
+        assert_eq!(entry.aggregated_status(), HealthStatus::Passing);
+        entry.Checks = Some(HealthChecks(vec![
+            check("serfHealth", Some("passing")),
+            check("service:web-1", Some("warning")),
+        ]));
+        assert_eq!(entry.aggregated_status(), HealthStatus::Warning);
+    }
+}
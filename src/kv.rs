@@ -0,0 +1,648 @@
+use super::api::{
+    append_query, append_query_flag, decode_json, decode_optional, require_ok, ConsulConfig,
+    QueryOptions, WriteOptions,
+};
+use super::error::ConsulError;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde_derive::{Deserialize, Serialize};
+use surf::http::Method;
+use surf::StatusCode;
+
+/// KVPair is used to represent a single K/V entry
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct KVPair {
+    /// Key is the name of the key. It is also part of the URL path when accessed
+    /// via the API.
+    pub Key: Option<String>,
+
+    /// CreateIndex holds the index corresponding the creation of this KVPair. This
+    /// is a read-only field.
+    pub CreateIndex: Option<u64>,
+
+    /// ModifyIndex is used for the Check-And-Set operations and can also be fed
+    /// back into the WaitIndex of the QueryOptions in order to perform blocking
+    /// queries.
+    pub ModifyIndex: Option<u64>,
+
+    /// LockIndex holds the index corresponding to a lock on this key, if any. This
+    /// is a read-only field.
+    pub LockIndex: Option<u64>,
+
+    /// Flags are any user-defined flags on the key. It is up to the implementer
+    /// to check these values, since Consul does not treat them specially.
+    pub Flags: Option<u64>,
+
+    /// Value is the value for the key. This can be any value, but it will be
+    /// base64 encoded upon transport.
+    #[serde(default, with = "base64_value")]
+    pub Value: Option<Vec<u8>>,
+
+    /// Session is a string representing the ID of the session. Any other
+    /// interactions with this key over the same session must specify the same
+    /// session ID.
+    pub Session: Option<String>,
+
+    /// Namespace is the namespace the KVPair is associated with
+    /// Namespacing is a Consul Enterprise feature.
+    pub Namespace: Option<String>,
+}
+
+/// KV_SET sets the Key to the given Value.
+pub const KV_SET: &str = "set";
+/// KV_DELETE deletes the Key.
+pub const KV_DELETE: &str = "delete";
+/// KV_CAS sets the Key to the given Value if the Index matches.
+pub const KV_CAS: &str = "cas";
+/// KV_LOCK locks the Key with the given Session.
+pub const KV_LOCK: &str = "lock";
+/// KV_UNLOCK unlocks the Key held by the given Session.
+pub const KV_UNLOCK: &str = "unlock";
+/// KV_GET returns the Key, failing the transaction if it does not exist.
+pub const KV_GET: &str = "get";
+/// KV_CHECK_INDEX fails the transaction if the Key's ModifyIndex does not
+/// match the Index.
+pub const KV_CHECK_INDEX: &str = "check-index";
+
+/// KVTxnOp defines a single operation inside a transaction.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct KVTxnOp {
+    /// Verb is one of the KV_* operations, such as KV_SET.
+    pub Verb: Option<String>,
+    pub Key: Option<String>,
+    #[serde(default, with = "base64_value")]
+    pub Value: Option<Vec<u8>>,
+    pub Flags: Option<u64>,
+    pub Index: Option<u64>,
+    pub Session: Option<String>,
+    pub Namespace: Option<String>,
+}
+
+/// TxnError is used to return information about an operation in
+/// a transaction.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct TxnError {
+    pub OpIndex: Option<usize>,
+    pub What: Option<String>,
+}
+
+/// KVTxnResponse has the outcome of a transaction. On success Results holds
+/// the pairs touched by each operation; on failure Errors tells which
+/// operations were rejected and nothing was applied.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct KVTxnResponse {
+    pub Results: Vec<KVPair>,
+    pub Errors: Vec<TxnError>,
+}
+
+impl KVTxnResponse {
+    /// is_ok reports whether the transaction was applied.
+    pub fn is_ok(&self) -> bool {
+        self.Errors.is_empty()
+    }
+}
+
+#[derive(Serialize)]
+#[allow(non_snake_case)]
+struct TxnOp<'a> {
+    KV: &'a KVTxnOp,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct TxnResult {
+    KV: Option<KVPair>,
+}
+
+#[derive(Default, Deserialize)]
+#[allow(non_snake_case)]
+struct TxnResponse {
+    Results: Option<Vec<TxnResult>>,
+    Errors: Option<Vec<TxnError>>,
+}
+
+/// base64_value (de)serializes the base64 encoded `Value` of a KVPair.
+pub(crate) mod base64_value {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Option<Vec<u8>>, s: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => s.serialize_str(&base64::encode(value)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<u8>>, D::Error> {
+        let value: Option<String> = Option::deserialize(d)?;
+        value
+            .map(|value| base64::decode(value).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// KEY is the set of characters escaped in a key. Only the `/` separating
+/// its segments and unreserved characters are left as is, so a `?`, `#` or
+/// `%` in a key reaches Consul as part of the key.
+const KEY: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// kv_path returns the request path of a key, percent-encoding each of its
+/// segments.
+fn kv_path(key: &str) -> String {
+    format!(
+        "/v1/kv/{}",
+        utf8_percent_encode(key.trim_start_matches('/'), KEY)
+    )
+}
+
+impl ConsulConfig {
+    /// kv_get is used to lookup a single key. A missing key is returned as
+    /// `None` rather than an error.
+    pub async fn kv_get(
+        &self,
+        key: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Option<KVPair>, ConsulError> {
+        let path = kv_path(key);
+        let mut req = self.new_request(Method::Get, &path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        let out: Option<Vec<KVPair>> = decode_optional(self.send(req).await?).await?;
+        Ok(out.and_then(|out| out.into_iter().next()))
+    }
+
+    /// kv_list is used to lookup all keys under a prefix.
+    pub async fn kv_list(
+        &self,
+        prefix: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<KVPair>, ConsulError> {
+        let path = kv_path(prefix);
+        let mut req = self.new_request(Method::Get, &path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        append_query_flag(&mut req, "recurse");
+        let out: Option<Vec<KVPair>> = decode_optional(self.send(req).await?).await?;
+        Ok(out.unwrap_or_default())
+    }
+
+    /// kv_keys is used to list all the keys under a prefix. Optionally,
+    /// a separator can be used to limit the responses.
+    pub async fn kv_keys(
+        &self,
+        prefix: &str,
+        separator: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<String>, ConsulError> {
+        let path = kv_path(prefix);
+        let mut req = self.new_request(Method::Get, &path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        append_query_flag(&mut req, "keys");
+        if !separator.is_empty() {
+            append_query(&mut req, "separator", separator);
+        }
+        let out: Option<Vec<String>> = decode_optional(self.send(req).await?).await?;
+        Ok(out.unwrap_or_default())
+    }
+
+    /// kv_put is used to write a new value. Only the Key, Flags and Value
+    /// are respected. The value is sent as the raw request body.
+    pub async fn kv_put(
+        &self,
+        pair: &KVPair,
+        q: Option<&WriteOptions>,
+    ) -> Result<bool, ConsulError> {
+        let req = self.kv_put_request(pair, q).await?;
+        self.kv_write(req).await
+    }
+
+    /// kv_delete is used to delete a single key, or every key under it when
+    /// `recurse` is set. Deleting a missing key is not an error.
+    pub async fn kv_delete(
+        &self,
+        key: &str,
+        recurse: bool,
+        q: Option<&WriteOptions>,
+    ) -> Result<bool, ConsulError> {
+        let path = kv_path(key);
+        let mut req = self.new_request(Method::Delete, &path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        if recurse {
+            append_query_flag(&mut req, "recurse");
+        }
+        let res = self.send(req).await?;
+        if res.status() == StatusCode::NotFound {
+            return Ok(true);
+        }
+        let mut res = require_ok(res).await?;
+        let out: bool = decode_json(&mut res).await?;
+        Ok(out)
+    }
+
+    /// kv_cas is used for a Check-And-Set operation. The write only succeeds
+    /// when the key's ModifyIndex still matches `cas_index`; a failed CAS
+    /// returns `false` rather than an error. An index of 0 only writes the key
+    /// if it does not exist yet.
+    pub async fn kv_cas(
+        &self,
+        pair: &KVPair,
+        cas_index: u64,
+        q: Option<&WriteOptions>,
+    ) -> Result<bool, ConsulError> {
+        let mut req = self.kv_put_request(pair, q).await?;
+        append_query(&mut req, "cas", &cas_index.to_string());
+        self.kv_write(req).await
+    }
+
+    /// kv_acquire is used for a lock acquisition operation. The session must
+    /// already exist; whether the lock was obtained is returned.
+    pub async fn kv_acquire(
+        &self,
+        pair: &KVPair,
+        session_id: &str,
+        q: Option<&WriteOptions>,
+    ) -> Result<bool, ConsulError> {
+        let mut req = self.kv_put_request(pair, q).await?;
+        append_query(&mut req, "acquire", session_id);
+        self.kv_write(req).await
+    }
+
+    /// kv_release is used for a lock release operation. Only the session
+    /// holding the lock can release it.
+    pub async fn kv_release(
+        &self,
+        pair: &KVPair,
+        session_id: &str,
+        q: Option<&WriteOptions>,
+    ) -> Result<bool, ConsulError> {
+        let mut req = self.kv_put_request(pair, q).await?;
+        append_query(&mut req, "release", session_id);
+        self.kv_write(req).await
+    }
+
+    /// kv_txn is used to apply multiple KV operations atomically. A rolled
+    /// back transaction is reported through the Errors of the response
+    /// rather than as an error.
+    pub async fn kv_txn(
+        &self,
+        ops: Vec<KVTxnOp>,
+        q: Option<&WriteOptions>,
+    ) -> Result<KVTxnResponse, ConsulError> {
+        let mut req = self.new_request(Method::Put, "/v1/txn").await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        let ops: Vec<TxnOp> = ops.iter().map(|op| TxnOp { KV: op }).collect();
+        req.body_json(&ops)?;
+        let res = self.send(req).await?;
+        // A transaction that was rolled back is answered with a conflict.
+        let mut res = if res.status() == StatusCode::Conflict {
+            res
+        } else {
+            require_ok(res).await?
+        };
+        let out: TxnResponse = decode_json(&mut res).await?;
+        Ok(KVTxnResponse {
+            Results: out
+                .Results
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|result| result.KV)
+                .collect(),
+            Errors: out.Errors.unwrap_or_default(),
+        })
+    }
+
+    async fn kv_put_request(
+        &self,
+        pair: &KVPair,
+        q: Option<&WriteOptions>,
+    ) -> Result<surf::Request, ConsulError> {
+        let key = match pair.Key.as_deref() {
+            Some(key) if !key.is_empty() => key,
+            _ => return Err(ConsulError::InvalidArgument(String::from("missing key"))),
+        };
+        let path = kv_path(key);
+        let mut req = self.new_request(Method::Put, &path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        if let Some(flags) = pair.Flags.filter(|flags| *flags != 0) {
+            append_query(&mut req, "flags", &flags.to_string());
+        }
+        req.set_body(pair.Value.clone().unwrap_or_default());
+        Ok(req)
+    }
+
+    /// kv_write sends a KV write and decodes the boolean Consul answers with.
+    async fn kv_write(&self, req: surf::Request) -> Result<bool, ConsulError> {
+        let mut res = require_ok(self.send(req).await?).await?;
+        let out: bool = decode_json(&mut res).await?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::api::{QueryOptions, WriteOptions};
+    use crate::error::ConsulError;
+    use crate::kv::{KVPair, KVTxnOp, KV_CAS, KV_SET};
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use serde_json::Value;
+
+    #[test]
+    fn test_kv_get() {
+        let body = r#"[{
+            "Key": "config/web/port",
+            "CreateIndex": 100,
+            "ModifyIndex": 200,
+            "LockIndex": 0,
+            "Flags": 42,
+            "Value": "ODA4MA==",
+            "Session": null
+        }]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body),
+            MockResponse::new(404, ""),
+        ]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            Datacenter: Some(String::from("dc2")),
+            ..Default::default()
+        };
+        let pair = block_on(consul.kv_get("config/web/port", Some(&q)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(pair.Key.as_deref(), Some("config/web/port"));
+        assert_eq!(pair.ModifyIndex, Some(200));
+        assert_eq!(pair.Flags, Some(42));
+        assert_eq!(pair.Value.as_deref(), Some(&b"8080"[..]));
+        assert!(pair.Session.is_none());
+
+        let missing = block_on(consul.kv_get("config/missing", None)).unwrap();
+        assert!(missing.is_none());
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/kv/config/web/port?dc=dc2");
+        assert_eq!(requests[1].path, "/v1/kv/config/missing");
+    }
+
+    #[test]
+    fn test_kv_put() {
+        let body = r#"[{"Key": "config/web/port", "Flags": 7, "Value": "ODA4MA=="}]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, "true"),
+            MockResponse::new(200, body),
+            MockResponse::new(403, "Permission denied"),
+        ]);
+        let consul = mock_consul(&server);
+        let pair = KVPair {
+            Key: Some(String::from("config/web/port")),
+            Flags: Some(7),
+            Value: Some(b"8080".to_vec()),
+            ..Default::default()
+        };
+        let q = WriteOptions {
+            Token: Some(String::from("secret")),
+            ..Default::default()
+        };
+        assert!(block_on(consul.kv_put(&pair, Some(&q))).unwrap());
+        let read = block_on(consul.kv_get("config/web/port", None))
+            .unwrap()
+            .unwrap();
+        assert_eq!(read.Value, pair.Value);
+        assert_eq!(read.Flags, Some(7));
+        let err = block_on(consul.kv_put(&pair, None)).unwrap_err();
+        assert_eq!(err.status(), Some(surf::StatusCode::Forbidden));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/v1/kv/config/web/port?flags=7");
+        assert_eq!(requests[0].header("X-Consul-Token"), Some("secret"));
+        assert_eq!(requests[0].body, b"8080");
+    }
+
+    #[test]
+    fn test_kv_put_missing_key() {
+        let consul = mock_consul(&MockServer::serve(vec![]));
+        let err = block_on(consul.kv_put(&KVPair::default(), None)).unwrap_err();
+        assert!(matches!(err, ConsulError::InvalidArgument(_)));
+    }
+
+    #[test]
+    fn test_kv_delete() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, "true"),
+            MockResponse::new(404, ""),
+            MockResponse::new(200, "true"),
+        ]);
+        let consul = mock_consul(&server);
+        assert!(block_on(consul.kv_delete("config/web/port", false, None)).unwrap());
+        assert!(block_on(consul.kv_delete("config/web/port", false, None)).unwrap());
+        assert!(block_on(consul.kv_delete("config/web/", true, None)).unwrap());
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "DELETE");
+        assert_eq!(requests[0].path, "/v1/kv/config/web/port");
+        assert_eq!(requests[2].method, "DELETE");
+        assert_eq!(requests[2].path, "/v1/kv/config/web/?recurse");
+    }
+
+    #[test]
+    fn test_kv_escaped_key() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(404, ""),
+            MockResponse::new(200, "true"),
+            MockResponse::new(200, "true"),
+            MockResponse::new(200, "true"),
+        ]);
+        let consul = mock_consul(&server);
+        let key = "/config/a?b#c d%2F/e";
+        assert!(block_on(consul.kv_get(key, None)).unwrap().is_none());
+        let pair = KVPair {
+            Key: Some(String::from(key)),
+            ..Default::default()
+        };
+        assert!(block_on(consul.kv_put(&pair, None)).unwrap());
+        assert!(block_on(consul.kv_acquire(&pair, "session", None)).unwrap());
+        assert!(block_on(consul.kv_delete(key, false, None)).unwrap());
+
+        let path = "/v1/kv/config/a%3Fb%23c%20d%252F/e";
+        let requests = server.requests();
+        assert_eq!(requests[0].path, path);
+        assert_eq!(requests[1].path, path);
+        assert_eq!(requests[2].path, format!("{}?acquire=session", path));
+        assert_eq!(requests[3].path, path);
+    }
+
+    #[test]
+    fn test_kv_list() {
+        let pairs = r#"[
+            {"Key": "config/web/host", "Value": "MTAuMC4wLjE="},
+            {"Key": "config/web/port", "Value": "ODA4MA=="},
+            {"Key": "config/web/tls/enabled", "Value": "dHJ1ZQ=="}
+        ]"#;
+        let keys = r#"["config/web/host", "config/web/port", "config/web/tls/"]"#;
+        let mut responses = vec![MockResponse::new(200, "true"); 3];
+        responses.push(MockResponse::new(200, pairs));
+        responses.push(MockResponse::new(200, keys));
+        responses.push(MockResponse::new(404, ""));
+        let server = MockServer::serve(responses);
+        let consul = mock_consul(&server);
+        for (key, value) in [
+            ("config/web/host", "10.0.0.1"),
+            ("config/web/port", "8080"),
+            ("config/web/tls/enabled", "true"),
+        ] {
+            let pair = KVPair {
+                Key: Some(String::from(key)),
+                Value: Some(value.as_bytes().to_vec()),
+                ..Default::default()
+            };
+            assert!(block_on(consul.kv_put(&pair, None)).unwrap());
+        }
+        let pairs = block_on(consul.kv_list("config/web/", None)).unwrap();
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(pairs[1].Key.as_deref(), Some("config/web/port"));
+        assert_eq!(pairs[2].Value.as_deref(), Some(&b"true"[..]));
+        let keys = block_on(consul.kv_keys("config/web/", "/", None)).unwrap();
+        assert_eq!(keys[2], "config/web/tls/");
+        let missing = block_on(consul.kv_list("config/db/", None)).unwrap();
+        assert!(missing.is_empty());
+
+        let requests = server.requests();
+        assert_eq!(requests[3].path, "/v1/kv/config/web/?recurse");
+        assert_eq!(requests[4].path, "/v1/kv/config/web/?keys&separator=%2F");
+    }
+
+    #[test]
+    fn test_kv_cas() {
+        let body = r#"[{"Key": "config/web/port", "ModifyIndex": 200, "Value": "ODA4MA=="}]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body),
+            MockResponse::new(200, "true"),
+            MockResponse::new(200, "false"),
+        ]);
+        let consul = mock_consul(&server);
+        let mut pair = block_on(consul.kv_get("config/web/port", None))
+            .unwrap()
+            .unwrap();
+        let index = pair.ModifyIndex.unwrap();
+        pair.Value = Some(b"9090".to_vec());
+        assert!(block_on(consul.kv_cas(&pair, index, None)).unwrap());
+        assert!(!block_on(consul.kv_cas(&pair, index, None)).unwrap());
+
+        let requests = server.requests();
+        assert_eq!(requests[1].path, "/v1/kv/config/web/port?cas=200");
+        assert_eq!(requests[1].body, b"9090");
+        assert_eq!(requests[2].path, "/v1/kv/config/web/port?cas=200");
+    }
+
+    #[test]
+    fn test_kv_acquire_release() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, "true"),
+            MockResponse::new(200, "false"),
+            MockResponse::new(200, "true"),
+        ]);
+        let consul = mock_consul(&server);
+        let pair = KVPair {
+            Key: Some(String::from("service/web/leader")),
+            Value: Some(b"node-a".to_vec()),
+            ..Default::default()
+        };
+        let first = "adf4238a-882b-9ddc-4a9d-5b6758e4159e";
+        let second = "b9b1b1c6-4b3a-4f2b-8c5d-2f9e0c1d7a33";
+        assert!(block_on(consul.kv_acquire(&pair, first, None)).unwrap());
+        assert!(!block_on(consul.kv_acquire(&pair, second, None)).unwrap());
+        assert!(block_on(consul.kv_release(&pair, first, None)).unwrap());
+
+        let requests = server.requests();
+        assert_eq!(
+            requests[0].path,
+            format!("/v1/kv/service/web/leader?acquire={}", first)
+        );
+        assert_eq!(
+            requests[1].path,
+            format!("/v1/kv/service/web/leader?acquire={}", second)
+        );
+        assert_eq!(
+            requests[2].path,
+            format!("/v1/kv/service/web/leader?release={}", first)
+        );
+    }
+
+    #[test]
+    fn test_kv_txn() {
+        let applied = r#"{
+            "Results": [
+                {"KV": {"Key": "config/web/host", "ModifyIndex": 300}},
+                {"KV": {"Key": "config/web/port", "ModifyIndex": 300}}
+            ],
+            "Errors": null
+        }"#;
+        let rolled_back = r#"{
+            "Results": null,
+            "Errors": [{"OpIndex": 0, "What": "failed to set key \"config/web/port\", index is stale"}]
+        }"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, applied),
+            MockResponse::new(409, rolled_back),
+        ]);
+        let consul = mock_consul(&server);
+        let ops = vec![
+            KVTxnOp {
+                Verb: Some(String::from(KV_SET)),
+                Key: Some(String::from("config/web/host")),
+                Value: Some(b"10.0.0.1".to_vec()),
+                ..Default::default()
+            },
+            KVTxnOp {
+                Verb: Some(String::from(KV_SET)),
+                Key: Some(String::from("config/web/port")),
+                Value: Some(b"8080".to_vec()),
+                ..Default::default()
+            },
+        ];
+        let res = block_on(consul.kv_txn(ops, None)).unwrap();
+        assert!(res.is_ok());
+        assert_eq!(res.Results.len(), 2);
+        assert_eq!(res.Results[1].Key.as_deref(), Some("config/web/port"));
+
+        let ops = vec![KVTxnOp {
+            Verb: Some(String::from(KV_CAS)),
+            Key: Some(String::from("config/web/port")),
+            Value: Some(b"9090".to_vec()),
+            Index: Some(200),
+            ..Default::default()
+        }];
+        let res = block_on(consul.kv_txn(ops, None)).unwrap();
+        assert!(!res.is_ok());
+        assert!(res.Results.is_empty());
+        assert_eq!(res.Errors[0].OpIndex, Some(0));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/v1/txn");
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body[0]["KV"]["Verb"], "set");
+        assert_eq!(body[0]["KV"]["Key"], "config/web/host");
+        assert_eq!(body[1]["KV"]["Value"], "ODA4MA==");
+    }
+}
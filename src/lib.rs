@@ -7,9 +7,25 @@ mod tests {
     }
 }
 
+pub mod acl;
 pub mod agent;
 pub mod api;
+pub mod cache;
 pub mod catalog;
 pub mod config_entry;
+pub mod connect;
+pub mod coordinate;
+pub mod error;
+pub mod event;
 pub mod health;
+pub mod kv;
+#[cfg(test)]
+mod mock;
+pub mod operator;
+pub mod prepared_query;
+pub mod runtime;
+pub mod serde;
+pub mod session;
+pub mod snapshot;
+pub mod status;
 pub mod watch;
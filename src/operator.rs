@@ -0,0 +1,109 @@
+use super::api::{append_query, require_ok, ConsulConfig, QueryOptions, WriteOptions};
+use super::error::ConsulError;
+use serde_derive::{Deserialize, Serialize};
+use surf::http::Method;
+
+/// RaftServer has information about a server in the Raft configuration.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct RaftServer {
+    /// ID is the unique ID for the server. These are currently the same
+    /// as the address, but they will be changed to a real GUID in a future
+    /// release of Consul.
+    pub ID: Option<String>,
+
+    /// Node is the node name of the server, as known by Consul, or this
+    /// will be set to "(unknown)" otherwise.
+    pub Node: Option<String>,
+
+    /// Address is the IP:port of the server, used for Raft communications.
+    pub Address: Option<String>,
+
+    /// Leader is true if this server is the current cluster leader.
+    pub Leader: Option<bool>,
+
+    /// Protocol version is the raft protocol version used by the server
+    pub ProtocolVersion: Option<String>,
+
+    /// Voter is true if this server has a vote in the cluster. This might
+    /// be false if the server is staging and still coming online, or if
+    /// it's a non-voting server, which will be added in a future release of
+    /// Consul.
+    pub Voter: Option<bool>,
+}
+
+/// RaftConfiguration is returned when querying for the current Raft configuration.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct RaftConfiguration {
+    /// Servers has the list of servers in the Raft configuration.
+    pub Servers: Option<Vec<RaftServer>>,
+
+    /// Index has the Raft index of this configuration.
+    pub Index: Option<u64>,
+}
+
+impl ConsulConfig {
+    /// operator_raft_config is used to query the current Raft peer set.
+    pub async fn operator_raft_config(
+        &self,
+        q: Option<&QueryOptions>,
+    ) -> Result<RaftConfiguration, ConsulError> {
+        self.get_json("/v1/operator/raft/configuration", q).await
+    }
+
+    /// operator_raft_remove_peer is used to kick a stale peer (one that is in
+    /// the Raft quorum but no longer known to Serf or the catalog) by address
+    /// in the form of "IP:port".
+    pub async fn operator_raft_remove_peer(
+        &self,
+        address: &str,
+        q: Option<&WriteOptions>,
+    ) -> Result<(), ConsulError> {
+        let mut req = self
+            .new_request(Method::Delete, "/v1/operator/raft/peer")
+            .await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        append_query(&mut req, "address", address);
+        require_ok(self.send(req).await?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+
+    #[test]
+    fn test_operator_raft_config() {
+        let body = r#"{
+            "Servers": [
+                {"ID": "a", "Node": "server-1", "Address": "10.1.0.1:8300", "Leader": true, "ProtocolVersion": "3", "Voter": true},
+                {"ID": "b", "Node": "server-2", "Address": "10.1.0.2:8300", "Leader": false, "ProtocolVersion": "3", "Voter": true}
+            ],
+            "Index": 22
+        }"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, body),
+            MockResponse::new(200, ""),
+        ]);
+        let consul = mock_consul(&server);
+        let config = block_on(consul.operator_raft_config(None)).unwrap();
+        let servers = config.Servers.unwrap();
+        assert!(servers.iter().any(|server| server.Leader == Some(true)));
+        assert_eq!(config.Index, Some(22));
+        block_on(consul.operator_raft_remove_peer("10.1.0.3:8300", None)).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/operator/raft/configuration");
+        assert_eq!(requests[1].method, "DELETE");
+        assert_eq!(
+            requests[1].path,
+            "/v1/operator/raft/peer?address=10.1.0.3%3A8300"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use super::api::{ConsulConfig, QueryOptions, WriteOptions};
+use super::error::ConsulError;
+use super::health::ServiceEntry;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use surf::http::Method;
+
+/// QueryFailoverOptions sets options about how we fail over if there are no
+/// healthy nodes in the local datacenter.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct QueryFailoverOptions {
+    /// NearestN is set to the number of remote datacenters to try, based on
+    /// network coordinates.
+    pub NearestN: Option<usize>,
+
+    /// Datacenters is a fixed list of datacenters to try after NearestN. We
+    /// never try a datacenter multiple times, so those are subtracted from
+    /// this list before proceeding.
+    pub Datacenters: Option<Vec<String>>,
+}
+
+/// ServiceQuery is used to query for a set of healthy nodes offering a
+/// specific service.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct ServiceQuery {
+    /// Service is the service to query.
+    pub Service: Option<String>,
+
+    /// Namespace of the service to query
+    pub Namespace: Option<String>,
+
+    /// Near allows baking in the name of a node to automatically distance-
+    /// sort from. The magic "_agent" value is supported, which sorts near
+    /// the agent which initiated the request by default.
+    pub Near: Option<String>,
+
+    /// Failover controls what we do if there are no healthy nodes in the
+    /// local datacenter.
+    pub Failover: Option<QueryFailoverOptions>,
+
+    /// IgnoreCheckIDs is an optional list of health check IDs to ignore when
+    /// considering which nodes are healthy. It is useful as an emergency measure
+    /// to temporarily override some health check that is producing false negatives
+    /// for example.
+    pub IgnoreCheckIDs: Option<Vec<String>>,
+
+    /// If OnlyPassing is true then we will only include nodes with passing
+    /// health checks (critical AND warning checks will cause a node to be
+    /// discarded)
+    pub OnlyPassing: Option<bool>,
+
+    /// Tags are a set of required and/or disallowed tags. If a tag is in
+    /// this list it must be present. If the tag is preceded with "!" then
+    /// it is disallowed.
+    pub Tags: Option<Vec<String>>,
+
+    /// NodeMeta is a map of required node metadata fields. If a key/value
+    /// pair is in this map it must be present on the node in order for the
+    /// service entry to be returned.
+    pub NodeMeta: Option<HashMap<String, String>>,
+
+    /// ServiceMeta is a map of required service metadata fields. If a key/value
+    /// pair is in this map it must be present on the node in order for the
+    /// service entry to be returned.
+    pub ServiceMeta: Option<HashMap<String, String>>,
+
+    /// Connect if true will filter the prepared query results to only
+    /// include Connect-capable services. These include both native services
+    /// and proxies for matching services. Note that if a proxy matches,
+    /// the constraints in the query above (Near, OnlyPassing, etc.) apply
+    /// to the _proxy_ and not the service being proxied. In practice, proxies
+    /// should be directly next to their services so this isn't an issue.
+    pub Connect: Option<bool>,
+}
+
+/// QueryTemplate carries the arguments for creating a templated query.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct QueryTemplate {
+    /// Type specifies the type of the query template. Currently only
+    /// "name_prefix_match" is supported. This field is required.
+    pub Type: Option<String>,
+
+    /// Regexp allows specifying a regex pattern to match against the name
+    /// of the query being executed.
+    pub Regexp: Option<String>,
+
+    /// RemoveEmptyTags if set to true, will cause the Tags list inside
+    /// the Service structure to be stripped of any empty strings.
+    pub RemoveEmptyTags: Option<bool>,
+}
+
+/// PreparedQueryDefinition defines a complete prepared query.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct PreparedQueryDefinition {
+    /// ID is this UUID-based ID for the query, always generated by Consul.
+    pub ID: Option<String>,
+
+    /// Name is an optional friendly name for the query supplied by the
+    /// user. NOTE - if this feature is used then it will reduce the security
+    /// of any read ACL associated with this query/service since this name
+    /// can be used to locate nodes with supplying any ACL.
+    pub Name: Option<String>,
+
+    /// Session is an optional session to tie this query's lifetime to. If
+    /// this is omitted then the query will not expire.
+    pub Session: Option<String>,
+
+    /// Token is the ACL token used when the query was created, and it is
+    /// used when a query is subsequently executed. This token, or a token
+    /// with management privileges, must be used to change the query later.
+    pub Token: Option<String>,
+
+    /// Service defines a service query (leaving things open for other types
+    /// later).
+    pub Service: Option<ServiceQuery>,
+
+    /// DNS has options that control how the results of this query are
+    /// served over DNS.
+    pub DNS: Option<QueryDNSOptions>,
+
+    /// Template is used to pass through the arguments for creating a
+    /// prepared query with an attached template. If a template is given,
+    /// interpolations are possible in other struct fields.
+    pub Template: Option<QueryTemplate>,
+}
+
+/// QueryDNSOptions controls settings when query results are served over DNS.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct QueryDNSOptions {
+    /// TTL is the time to live for the served DNS results.
+    pub TTL: Option<String>,
+}
+
+/// PreparedQueryExecuteResponse has the results of executing a query.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct PreparedQueryExecuteResponse {
+    /// Service is the service that was queried.
+    pub Service: Option<String>,
+
+    /// Namespace of the service that was queried
+    pub Namespace: Option<String>,
+
+    /// Nodes has the nodes that were output by the query.
+    pub Nodes: Option<Vec<ServiceEntry>>,
+
+    /// DNS has the options for serving these results over DNS.
+    pub DNS: Option<QueryDNSOptions>,
+
+    /// Datacenter is the datacenter that these results came from.
+    pub Datacenter: Option<String>,
+
+    /// Failovers is a count of how many times we had to query a remote
+    /// datacenter.
+    pub Failovers: Option<i32>,
+}
+
+/// QueryID is the answer Consul gives when a prepared query is created.
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+struct QueryID {
+    ID: String,
+}
+
+impl ConsulConfig {
+    /// prepared_query_create makes a new prepared query. The ID of the new
+    /// query is returned.
+    pub async fn prepared_query_create(
+        &self,
+        query: &PreparedQueryDefinition,
+        q: Option<&WriteOptions>,
+    ) -> Result<String, ConsulError> {
+        let (out, _): (QueryID, _) = self.write_json(Method::Post, "/v1/query", query, q).await?;
+        Ok(out.ID)
+    }
+
+    /// prepared_query_list is used to fetch all the prepared queries (always
+    /// requires a management token).
+    pub async fn prepared_query_list(
+        &self,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<PreparedQueryDefinition>, ConsulError> {
+        self.get_json("/v1/query", q).await
+    }
+
+    /// prepared_query_execute is used to execute a specific prepared query. You
+    /// can execute using a query ID or name.
+    pub async fn prepared_query_execute(
+        &self,
+        query_id_or_name: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<PreparedQueryExecuteResponse, ConsulError> {
+        let path = format!("/v1/query/{}/execute", query_id_or_name);
+        self.get_json(&path, q).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::prepared_query::{
+        PreparedQueryDefinition, QueryDNSOptions, QueryFailoverOptions, ServiceQuery,
+    };
+    use crate::runtime::block_on;
+    use serde_json::Value;
+
+    #[test]
+    fn test_prepared_query_execute() {
+        let body = r#"{
+            "Service": "web",
+            "Nodes": [
+                {
+                    "Node": {"Node": "node-b", "Address": "10.2.10.12", "Datacenter": "dc2"},
+                    "Service": {"ID": "web-1", "Service": "web", "Port": 8080},
+                    "Checks": []
+                }
+            ],
+            "DNS": {"TTL": "10s"},
+            "Datacenter": "dc2",
+            "Failovers": 1
+        }"#;
+        let server = MockServer::serve(vec![MockResponse::new(200, body)]);
+        let consul = mock_consul(&server);
+        let res = block_on(consul.prepared_query_execute("web-failover", None)).unwrap();
+        let nodes = res.Nodes.unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(
+            nodes[0].Node.as_ref().unwrap().Node.as_deref(),
+            Some("node-b")
+        );
+        assert_eq!(nodes[0].Service.as_ref().unwrap().Port, Some(8080));
+        assert_eq!(res.DNS.unwrap().TTL.as_deref(), Some("10s"));
+        assert_eq!(res.Datacenter.as_deref(), Some("dc2"));
+        assert_eq!(res.Failovers, Some(1));
+        assert_eq!(server.requests()[0].path, "/v1/query/web-failover/execute");
+    }
+
+    #[test]
+    fn test_prepared_query_create_and_list() {
+        let list = r#"[{
+            "ID": "8f246b77-f3e1-ff88-5b48-8ec93abf3e05",
+            "Name": "web-failover",
+            "Service": {
+                "Service": "web",
+                "Failover": {"NearestN": 3, "Datacenters": ["dc2", "dc3"]},
+                "OnlyPassing": true,
+                "Tags": ["v1", "!canary"]
+            },
+            "DNS": {"TTL": "10s"}
+        }]"#;
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, r#"{"ID": "8f246b77-f3e1-ff88-5b48-8ec93abf3e05"}"#),
+            MockResponse::new(200, list),
+        ]);
+        let consul = mock_consul(&server);
+        let query = PreparedQueryDefinition {
+            Name: Some(String::from("web-failover")),
+            Service: Some(ServiceQuery {
+                Service: Some(String::from("web")),
+                Failover: Some(QueryFailoverOptions {
+                    NearestN: Some(3),
+                    Datacenters: Some(vec![String::from("dc2"), String::from("dc3")]),
+                }),
+                OnlyPassing: Some(true),
+                Tags: Some(vec![String::from("v1"), String::from("!canary")]),
+                ..Default::default()
+            }),
+            DNS: Some(QueryDNSOptions {
+                TTL: Some(String::from("10s")),
+            }),
+            ..Default::default()
+        };
+        let id = block_on(consul.prepared_query_create(&query, None)).unwrap();
+        let queries = block_on(consul.prepared_query_list(None)).unwrap();
+        let found = queries
+            .iter()
+            .find(|query| query.ID.as_deref() == Some(id.as_str()))
+            .unwrap();
+        assert_eq!(found.Name.as_deref(), Some("web-failover"));
+        let service = found.Service.as_ref().unwrap();
+        assert_eq!(service.Failover.as_ref().unwrap().NearestN, Some(3));
+        assert_eq!(service.OnlyPassing, Some(true));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/v1/query");
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["Service"]["Service"], "web");
+        assert_eq!(body["Service"]["Failover"]["Datacenters"][1], "dc3");
+        assert_eq!(body["DNS"]["TTL"], "10s");
+        assert_eq!(requests[1].method, "GET");
+        assert_eq!(requests[1].path, "/v1/query");
+    }
+}
@@ -0,0 +1,33 @@
+//! The runtime specific pieces of the crate, picked by the `async-std`
+//! (default) or `tokio` feature. Requests go through surf's isahc backend,
+//! which drives its own I/O, so only locking, timers and file reads differ.
+#[cfg(not(any(feature = "async-std", feature = "tokio")))]
+compile_error!("either the `async-std` or the `tokio` feature must be enabled");
+
+#[cfg(feature = "async-std")]
+pub use async_std::sync::RwLock;
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+pub use tokio::sync::RwLock;
+
+#[cfg(feature = "async-std")]
+pub(crate) use async_std::fs::read_to_string;
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+pub(crate) use tokio::fs::read_to_string;
+
+#[cfg(feature = "async-std")]
+pub(crate) use async_std::task::sleep;
+#[cfg(all(feature = "tokio", not(feature = "async-std")))]
+pub(crate) use tokio::time::sleep;
+
+/// block_on runs a future to completion on the runtime picked by the
+/// enabled feature, so tests exercise the same timers and locks as users.
+#[cfg(all(test, feature = "async-std"))]
+pub(crate) use async_std::task::block_on;
+#[cfg(all(test, feature = "tokio", not(feature = "async-std")))]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
@@ -0,0 +1,267 @@
+//! Serde helpers for the Go formats Consul uses on the wire.
+use serde::de::{Deserializer, Error, Visitor};
+use serde::Serializer;
+use std::fmt;
+use std::time::Duration;
+
+/// deserialize_duration decodes an optional Go duration such as "1m30s".
+/// Integers are taken as nanoseconds, which is how Go encodes a
+/// time.Duration in JSON. Use it together with `#[serde(default)]` so an
+/// absent field is `None` as well.
+pub fn deserialize_duration<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+    d.deserialize_option(OptionDurationVisitor)
+}
+
+/// serialize_duration encodes an optional duration as a Go duration string
+/// such as "1m30s", the format Consul expects in write requests.
+pub fn serialize_duration<S: Serializer>(
+    value: &Option<Duration>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(value) => s.serialize_str(&format_duration(*value)),
+        None => s.serialize_none(),
+    }
+}
+
+/// format_duration renders a duration the way Go's time.Duration.String
+/// does, e.g. "1h2m0.5s", "1.5ms" or "0s".
+pub fn format_duration(d: Duration) -> String {
+    let nanos = d.as_nanos();
+    if nanos == 0 {
+        return String::from("0s");
+    }
+    if nanos < 1_000_000_000 {
+        let (unit, scale) = if nanos < 1_000 {
+            ("ns", 1)
+        } else if nanos < 1_000_000 {
+            ("µs", 1_000)
+        } else {
+            ("ms", 1_000_000)
+        };
+        return format!("{}{}", format_fraction(nanos, scale), unit);
+    }
+    let hours = nanos / 3_600_000_000_000;
+    let minutes = nanos / 60_000_000_000 % 60;
+    let seconds = format_fraction(nanos % 60_000_000_000, 1_000_000_000);
+    if hours > 0 {
+        format!("{}h{}m{}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// format_fraction prints value / scale as a decimal without trailing zeros.
+fn format_fraction(value: u128, scale: u128) -> String {
+    let (int, frac) = (value / scale, value % scale);
+    if frac == 0 {
+        return int.to_string();
+    }
+    let width = scale.to_string().len() - 1;
+    let frac = format!("{:0width$}", frac, width = width);
+    format!("{}.{}", int, frac.trim_end_matches('0'))
+}
+
+/// parse_duration parses a Go duration string: a sequence of decimal
+/// numbers, each with an optional fraction and a unit suffix, such as "300ms"
+/// or "1h45m". Valid units are "ns", "us" (or "µs"), "ms", "s", "m" and "h".
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration {:?}", s);
+    let mut rest = s.strip_prefix('+').unwrap_or(s);
+    if rest == "0" {
+        return Ok(Duration::from_secs(0));
+    }
+    if rest.is_empty() || rest.starts_with('-') {
+        return Err(invalid());
+    }
+    let mut nanos: u128 = 0;
+    while !rest.is_empty() {
+        let int_len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (int, tail) = rest.split_at(int_len);
+        let (frac, tail) = match tail.strip_prefix('.') {
+            Some(tail) => {
+                let frac_len = tail
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(tail.len());
+                tail.split_at(frac_len)
+            }
+            None => ("", tail),
+        };
+        if int.is_empty() && frac.is_empty() {
+            return Err(invalid());
+        }
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let unit: u128 = match unit {
+            "ns" => 1,
+            "us" | "µs" | "μs" => 1_000,
+            "ms" => 1_000_000,
+            "s" => 1_000_000_000,
+            "m" => 60 * 1_000_000_000,
+            "h" => 60 * 60 * 1_000_000_000,
+            _ => return Err(invalid()),
+        };
+        let int: u128 = if int.is_empty() {
+            0
+        } else {
+            int.parse().map_err(|_| invalid())?
+        };
+        nanos = int
+            .checked_mul(unit)
+            .and_then(|value| nanos.checked_add(value))
+            .ok_or_else(invalid)?;
+        if !frac.is_empty() {
+            // Digits past the nanosecond precision of the unit are dropped.
+            let digits = &frac[..frac.len().min(18)];
+            let scale = 10u128.pow(digits.len() as u32);
+            let frac: u128 = digits.parse().map_err(|_| invalid())?;
+            nanos += frac * unit / scale;
+        }
+        rest = tail;
+    }
+    if nanos > u128::from(u64::MAX) {
+        return Err(invalid());
+    }
+    Ok(Duration::from_nanos(nanos as u64))
+}
+
+struct OptionDurationVisitor;
+
+impl<'de> Visitor<'de> for OptionDurationVisitor {
+    type Value = Option<Duration>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a Go duration string, nanoseconds or null")
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        d.deserialize_any(DurationVisitor).map(Some)
+    }
+}
+
+struct DurationVisitor;
+
+impl<'de> Visitor<'de> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a Go duration string or nanoseconds")
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        parse_duration(v).map_err(E::custom)
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Duration::from_nanos(v))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        if v < 0 {
+            return Err(E::custom(format!("negative duration {}", v)));
+        }
+        Ok(Duration::from_nanos(v as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::health::HealthCheckDefinition;
+    use crate::serde::{format_duration, parse_duration};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1m30s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::from_secs(0)));
+        assert_eq!(parse_duration("0"), Ok(Duration::from_secs(0)));
+        assert_eq!(parse_duration("1.5h"), Ok(Duration::from_secs(5400)));
+        assert_eq!(
+            parse_duration("2h45m0.5s"),
+            Ok(Duration::from_millis(9_900_500))
+        );
+        assert_eq!(parse_duration("10us"), Ok(Duration::from_micros(10)));
+        assert_eq!(parse_duration("10µs"), Ok(Duration::from_micros(10)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("1d").is_err());
+        assert!(parse_duration(".s").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_duration() {
+        let definition: HealthCheckDefinition = serde_json::from_str(
+            r#"{
+                "HTTP": "http://127.0.0.1:8080/health",
+                "Interval": "10s",
+                "Timeout": "500ms",
+                "DeregisterCriticalServiceAfter": null
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(definition.Interval, Some(Duration::from_secs(10)));
+        assert_eq!(definition.Timeout, Some(Duration::from_millis(500)));
+        assert_eq!(definition.DeregisterCriticalServiceAfter, None);
+
+        let definition: HealthCheckDefinition =
+            serde_json::from_str(r#"{"Interval": "1m30s"}"#).unwrap();
+        assert_eq!(definition.Interval, Some(Duration::from_secs(90)));
+        assert_eq!(definition.Timeout, None);
+
+        let err = serde_json::from_str::<HealthCheckDefinition>(r#"{"Interval": "soon"}"#);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(30)), "30s");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h0m0s");
+        assert_eq!(
+            format_duration(Duration::from_millis(9_900_500)),
+            "2h45m0.5s"
+        );
+        assert_eq!(format_duration(Duration::from_millis(500)), "500ms");
+        assert_eq!(format_duration(Duration::from_micros(1_500)), "1.5ms");
+        assert_eq!(format_duration(Duration::from_nanos(1_500)), "1.5µs");
+        assert_eq!(format_duration(Duration::from_nanos(42)), "42ns");
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        let definition = HealthCheckDefinition {
+            IntervalDuration: Some(Duration::from_secs(10)),
+            Interval: Some(Duration::from_secs(90)),
+            Timeout: Some(Duration::from_millis(1_500)),
+            ..Default::default()
+        };
+        let body = serde_json::to_value(&definition).unwrap();
+        assert_eq!(body["Interval"], "10s");
+        assert_eq!(body["Timeout"], "1.5s");
+        assert!(body.get("IntervalDuration").is_none());
+        assert!(body.get("DeregisterCriticalServiceAfter").is_none());
+
+        let decoded: HealthCheckDefinition = serde_json::from_value(body).unwrap();
+        assert_eq!(decoded.Interval, definition.IntervalDuration);
+        assert_eq!(decoded.Timeout, definition.Timeout);
+        assert_eq!(decoded.IntervalDuration, None);
+        assert_eq!(decoded.DeregisterCriticalServiceAfter, None);
+    }
+}
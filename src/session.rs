@@ -0,0 +1,255 @@
+use super::api::{decode_json, require_ok, ConsulConfig, QueryOptions, WriteOptions};
+use super::error::ConsulError;
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
+use surf::http::Method;
+use surf::StatusCode;
+
+/// SESSION_BEHAVIOR_RELEASE is the default behavior and causes
+/// all associated locks to be released on session invalidation.
+pub const SESSION_BEHAVIOR_RELEASE: &str = "release";
+
+/// SESSION_BEHAVIOR_DELETE is new in Consul 0.5 and changes the
+/// behavior to delete all associated locks on session invalidation.
+/// It can be used in a way similar to Ephemeral Nodes in ZooKeeper.
+pub const SESSION_BEHAVIOR_DELETE: &str = "delete";
+
+/// SessionEntry represents a session in consul
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub struct SessionEntry {
+    pub CreateIndex: Option<u64>,
+    pub ID: Option<String>,
+    pub Name: Option<String>,
+    pub Node: Option<String>,
+    pub Checks: Option<Vec<String>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::serde::serialize_duration",
+        deserialize_with = "crate::serde::deserialize_duration"
+    )]
+    pub LockDelay: Option<Duration>,
+    /// Behavior is one of SESSION_BEHAVIOR_RELEASE or SESSION_BEHAVIOR_DELETE.
+    pub Behavior: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "crate::serde::serialize_duration",
+        deserialize_with = "crate::serde::deserialize_duration"
+    )]
+    pub TTL: Option<Duration>,
+    pub Namespace: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[allow(non_snake_case)]
+struct SessionID {
+    ID: String,
+}
+
+impl ConsulConfig {
+    /// session_create makes a new session and returns its ID.
+    pub async fn session_create(
+        &self,
+        entry: &SessionEntry,
+        q: Option<&WriteOptions>,
+    ) -> Result<String, ConsulError> {
+        let (out, _): (SessionID, _) = self
+            .write_json(Method::Put, "/v1/session/create", entry, q)
+            .await?;
+        Ok(out.ID)
+    }
+
+    /// session_destroy invalidates a given session.
+    pub async fn session_destroy(
+        &self,
+        id: &str,
+        q: Option<&WriteOptions>,
+    ) -> Result<(), ConsulError> {
+        let path = format!("/v1/session/destroy/{}", id);
+        let mut req = self.new_request(Method::Put, &path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        require_ok(self.send(req).await?).await?;
+        Ok(())
+    }
+
+    /// session_renew renews the TTL on a given session.
+    pub async fn session_renew(
+        &self,
+        id: &str,
+        q: Option<&WriteOptions>,
+    ) -> Result<SessionEntry, ConsulError> {
+        let path = format!("/v1/session/renew/{}", id);
+        let mut req = self.new_request(Method::Put, &path).await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        let mut res = require_ok(self.send(req).await?).await?;
+        let mut out: Vec<SessionEntry> = decode_json(&mut res).await?;
+        if out.is_empty() {
+            return Err(ConsulError::Http {
+                status: StatusCode::NotFound,
+                body: format!("Session {:?} not found", id),
+            });
+        }
+        Ok(out.remove(0))
+    }
+
+    /// session_info looks up a single session. An unknown session is
+    /// returned as `None`.
+    pub async fn session_info(
+        &self,
+        id: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Option<SessionEntry>, ConsulError> {
+        let mut out = self
+            .session_entries(&format!("/v1/session/info/{}", id), q)
+            .await?;
+        if out.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(out.remove(0)))
+    }
+
+    /// session_node gets sessions for a node.
+    pub async fn session_node(
+        &self,
+        node: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<SessionEntry>, ConsulError> {
+        self.session_entries(&format!("/v1/session/node/{}", node), q)
+            .await
+    }
+
+    /// session_list gets all active sessions.
+    pub async fn session_list(
+        &self,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<SessionEntry>, ConsulError> {
+        self.session_entries("/v1/session/list", q).await
+    }
+
+    async fn session_entries(
+        &self,
+        path: &str,
+        q: Option<&QueryOptions>,
+    ) -> Result<Vec<SessionEntry>, ConsulError> {
+        self.get_json(path, q).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use crate::session::{SessionEntry, SESSION_BEHAVIOR_DELETE};
+    use serde_json::Value;
+    use std::time::Duration;
+
+    const SESSION_ID: &str = "adf4238a-882b-9ddc-4a9d-5b6758e4159e";
+
+    #[test]
+    fn test_session_create_destroy() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, &format!(r#"{{"ID": "{}"}}"#, SESSION_ID)),
+            MockResponse::new(200, "true"),
+            MockResponse::new(200, &format!(r#"{{"ID": "{}"}}"#, SESSION_ID)),
+        ]);
+        let consul = mock_consul(&server);
+        let entry = SessionEntry {
+            Name: Some(String::from("web-leader")),
+            Behavior: Some(String::from(SESSION_BEHAVIOR_DELETE)),
+            LockDelay: Some(Duration::from_secs(15)),
+            TTL: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        let id = block_on(consul.session_create(&entry, None)).unwrap();
+        assert_eq!(id, SESSION_ID);
+        block_on(consul.session_destroy(&id, None)).unwrap();
+        block_on(consul.session_create(&SessionEntry::default(), None)).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/v1/session/create");
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["Name"], "web-leader");
+        assert_eq!(body["Behavior"], "delete");
+        assert_eq!(body["LockDelay"], "15s");
+        assert_eq!(body["TTL"], "30s");
+        assert_eq!(requests[1].method, "PUT");
+        assert_eq!(
+            requests[1].path,
+            format!("/v1/session/destroy/{}", SESSION_ID)
+        );
+        // Unset durations are left out rather than sent as null.
+        let body: Value = serde_json::from_slice(&requests[2].body).unwrap();
+        assert!(body.get("LockDelay").is_none());
+        assert!(body.get("TTL").is_none());
+    }
+
+    #[test]
+    fn test_session_renew() {
+        let renewed = format!(
+            r#"[{{"ID": "{}", "Name": "web-leader", "LockDelay": 15000000000, "TTL": "30s"}}]"#,
+            SESSION_ID
+        );
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, &renewed),
+            MockResponse::new(200, &renewed),
+            MockResponse::new(404, "Session id not found"),
+        ]);
+        let consul = mock_consul(&server);
+        for _ in 0..2 {
+            let entry = block_on(consul.session_renew(SESSION_ID, None)).unwrap();
+            assert_eq!(entry.ID.as_deref(), Some(SESSION_ID));
+            assert_eq!(entry.LockDelay, Some(Duration::from_secs(15)));
+            assert_eq!(entry.TTL, Some(Duration::from_secs(30)));
+        }
+        let err = block_on(consul.session_renew(SESSION_ID, None)).unwrap_err();
+        assert_eq!(err.status(), Some(surf::StatusCode::NotFound));
+        assert_eq!(
+            server.requests()[0].path,
+            format!("/v1/session/renew/{}", SESSION_ID)
+        );
+    }
+
+    #[test]
+    fn test_session_list() {
+        let entries = format!(
+            r#"[{{"ID": "{}", "Name": "web-leader", "Node": "node-a"}}]"#,
+            SESSION_ID
+        );
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, &format!(r#"{{"ID": "{}"}}"#, SESSION_ID)),
+            MockResponse::new(200, &entries),
+            MockResponse::new(200, &entries),
+            MockResponse::new(200, &entries),
+            MockResponse::new(200, "[]"),
+        ]);
+        let consul = mock_consul(&server);
+        let entry = SessionEntry {
+            Name: Some(String::from("web-leader")),
+            ..Default::default()
+        };
+        let id = block_on(consul.session_create(&entry, None)).unwrap();
+        let sessions = block_on(consul.session_list(None)).unwrap();
+        assert!(sessions
+            .iter()
+            .any(|session| session.ID.as_deref() == Some(id.as_str())));
+        let sessions = block_on(consul.session_node("node-a", None)).unwrap();
+        assert_eq!(sessions[0].Node.as_deref(), Some("node-a"));
+        let info = block_on(consul.session_info(&id, None)).unwrap().unwrap();
+        assert_eq!(info.Name.as_deref(), Some("web-leader"));
+        let missing = block_on(consul.session_info(&id, None)).unwrap();
+        assert!(missing.is_none());
+
+        let requests = server.requests();
+        assert_eq!(requests[1].path, "/v1/session/list");
+        assert_eq!(requests[2].path, "/v1/session/node/node-a");
+        assert_eq!(requests[3].path, format!("/v1/session/info/{}", id));
+    }
+}
@@ -0,0 +1,99 @@
+use super::api::{require_ok, ConsulConfig, QueryOptions, WriteOptions};
+use super::error::ConsulError;
+use futures_util::io::{AllowStdIo, AsyncReadExt};
+use std::io::{BufReader, Read, Write};
+use surf::http::Method;
+
+impl ConsulConfig {
+    /// snapshot_save_to requests a new snapshot and streams it, a gzipped
+    /// tar archive, to the writer in chunks rather than holding it in
+    /// memory. It returns the number of bytes written.
+    pub async fn snapshot_save_to<W: Write>(
+        &self,
+        writer: &mut W,
+        q: Option<&QueryOptions>,
+    ) -> Result<u64, ConsulError> {
+        let mut req = self.new_request(Method::Get, "/v1/snapshot").await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        let mut res = require_ok(self.send(req).await?).await?;
+        let mut body = res.take_body();
+        let mut buf = vec![0; 64 * 1024];
+        let mut written = 0;
+        loop {
+            let n = body.read(&mut buf).await.map_err(surf::Error::from)?;
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buf[..n]).map_err(surf::Error::from)?;
+            written += n as u64;
+        }
+        writer.flush().map_err(surf::Error::from)?;
+        Ok(written)
+    }
+
+    /// snapshot_restore_from streams a snapshot previously saved with
+    /// snapshot_save_to back to the servers, replacing their state.
+    pub async fn snapshot_restore_from<R: Read + Send + Sync + Unpin + 'static>(
+        &self,
+        reader: R,
+        q: Option<&WriteOptions>,
+    ) -> Result<(), ConsulError> {
+        let mut req = self.new_request(Method::Put, "/v1/snapshot").await?;
+        if let Some(q) = q {
+            q.apply(&mut req);
+        }
+        let reader = AllowStdIo::new(BufReader::new(reader));
+        req.set_body(surf::Body::from_reader(reader, None));
+        require_ok(self.send(req).await?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_snapshot_save_to() {
+        // Large enough to take several reads of the body.
+        let snapshot: String = (0..200_000)
+            .map(|i| (b'a' + (i % 26) as u8) as char)
+            .collect();
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, &snapshot).header("X-Consul-Index", "42"),
+            MockResponse::new(403, "Permission denied"),
+        ]);
+        let consul = mock_consul(&server);
+        let mut buf = vec![];
+        let written = block_on(consul.snapshot_save_to(&mut buf, None)).unwrap();
+        assert_eq!(written, snapshot.len() as u64);
+        assert_eq!(buf, snapshot.as_bytes());
+
+        let mut buf = vec![];
+        let err = block_on(consul.snapshot_save_to(&mut buf, None)).unwrap_err();
+        assert_eq!(err.status(), Some(surf::StatusCode::Forbidden));
+        assert!(buf.is_empty());
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/v1/snapshot");
+    }
+
+    #[test]
+    fn test_snapshot_restore_from() {
+        let server = MockServer::serve(vec![MockResponse::new(200, "")]);
+        let consul = mock_consul(&server);
+        let snapshot = b"\x1f\x8bsnapshot archive".to_vec();
+        block_on(consul.snapshot_restore_from(Cursor::new(snapshot.clone()), None)).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/v1/snapshot");
+        assert_eq!(requests[0].body, snapshot);
+    }
+}
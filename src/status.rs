@@ -0,0 +1,45 @@
+use super::api::{ConsulConfig, QueryOptions};
+use super::error::ConsulError;
+
+impl ConsulConfig {
+    /// status_leader is used to query for a known leader. The address is
+    /// empty while the cluster has no leader.
+    pub async fn status_leader(&self, q: Option<&QueryOptions>) -> Result<String, ConsulError> {
+        self.get_json("/v1/status/leader", q).await
+    }
+
+    /// status_peers is used to query for the known raft peers.
+    pub async fn status_peers(&self, q: Option<&QueryOptions>) -> Result<Vec<String>, ConsulError> {
+        self.get_json("/v1/status/peers", q).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::tests::mock_consul;
+    use crate::api::QueryOptions;
+    use crate::mock::{MockResponse, MockServer};
+    use crate::runtime::block_on;
+
+    #[test]
+    fn test_status() {
+        let server = MockServer::serve(vec![
+            MockResponse::new(200, r#""10.1.10.12:8300""#),
+            MockResponse::new(200, r#"["10.1.10.12:8300", "10.1.10.11:8300"]"#),
+        ]);
+        let consul = mock_consul(&server);
+        let q = QueryOptions {
+            Datacenter: Some(String::from("dc2")),
+            ..Default::default()
+        };
+        let leader = block_on(consul.status_leader(Some(&q))).unwrap();
+        assert!(!leader.is_empty());
+        let peers = block_on(consul.status_peers(Some(&q))).unwrap();
+        assert!(!peers.is_empty());
+        assert!(peers.iter().all(|peer| !peer.is_empty()));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/v1/status/leader?dc=dc2");
+        assert_eq!(requests[1].path, "/v1/status/peers?dc=dc2");
+    }
+}
@@ -1,11 +1,13 @@
 use serde_derive::{Deserialize, Serialize};
 
-pub const ROUND_ROBIN: &'static str = "round_robin";
-pub const RANDOM: &'static str = "random";
+pub const ROUND_ROBIN: &str = "round_robin";
+pub const RANDOM: &str = "random";
 
 #[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct WatchService {
     pub service_name: String,
     pub tag: Option<String>,
     pub passing_only: Option<bool>,
+    /// sort orders the resolved addresses so the list is stable between polls.
+    pub sort: Option<bool>,
 }